@@ -2,33 +2,86 @@ use std::{collections::HashMap, path::PathBuf};
 
 use cargo_metadata::DependencyKind;
 
+#[path = "src/assets/validation.rs"]
+mod validation;
+
 // Compress the assets from game + mods into an archive that is included in the executable.
 fn main() {
     println!("cargo:rerun-if-changed=assets");
+    println!("cargo:rerun-if-changed=src/assets/validation.rs");
 
     let mut asset_paths = HashMap::new();
-    for asset_path in get_asset_paths() {
-        for asset in walk_dir(asset_path.join("assets/client")) {
-            let relative_asset_path = asset.strip_prefix(&asset_path).unwrap().to_path_buf();
-            asset_paths.insert(relative_asset_path, asset);
-        }
+    // Which package each asset comes from, and the packages whose version of it was overridden.
+    let mut asset_origins: HashMap<PathBuf, (String, Vec<String>)> = HashMap::new();
+    for (package_name, asset_path) in get_asset_paths() {
+        let mut assets = walk_dir(asset_path.join("assets/client"));
+        assets.extend(walk_dir(asset_path.join("assets/server")));
 
-        for asset in walk_dir(asset_path.join("assets/server")) {
+        for asset in assets {
             let relative_asset_path = asset.strip_prefix(&asset_path).unwrap().to_path_buf();
-            asset_paths.insert(relative_asset_path, asset);
+            asset_paths.insert(relative_asset_path.clone(), asset);
+
+            if let Some((origin, overridden)) = asset_origins.get_mut(&relative_asset_path) {
+                println!(
+                    "cargo:warning=The asset '{}' from '{}' overrides the one from '{}'",
+                    relative_asset_path.display(),
+                    package_name,
+                    origin
+                );
+                overridden.push(std::mem::replace(origin, package_name.clone()));
+            } else {
+                asset_origins.insert(relative_asset_path, (package_name.clone(), Vec::new()));
+            }
         }
     }
 
+    let mut relative_paths: Vec<PathBuf> = asset_paths.keys().cloned().collect();
+    relative_paths.sort();
+    let errors = validation::validate(&relative_paths, |path| {
+        std::fs::read_to_string(&asset_paths[path]).ok()
+    });
+    if !errors.is_empty() {
+        panic!(
+            "The assets refer to files, blocks or items that don't exist:\n{}",
+            validation::format_errors(&errors)
+        );
+    }
+
+    // Printed by the server with '--list-assets'
+    let mut origins = String::new();
+    for path in relative_paths.iter() {
+        let (origin, overridden) = &asset_origins[path];
+        origins += &format!("{} {}", path.display(), origin);
+        if !overridden.is_empty() {
+            origins += &format!(" (overrides {})", overridden.join(", "));
+        }
+        origins += "\n";
+    }
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    std::fs::write(
+        std::path::Path::new(&out_dir).join("asset_origins.txt"),
+        origins,
+    )
+    .unwrap();
+
+    // The entries are sorted and their metadata (timestamps, owners, permissions) normalized so
+    // the same assets always give the same archive.
     let mut archive = tar::Builder::new(Vec::new());
-    for (relative_path, absolute_path) in asset_paths {
+    archive.mode(tar::HeaderMode::Deterministic);
+    for relative_path in relative_paths.iter() {
         archive
-            .append_path_with_name(absolute_path, relative_path)
+            .append_path_with_name(&asset_paths[relative_path], relative_path)
             .unwrap();
     }
+    let archive = archive.into_inner().unwrap();
 
-    let out_dir = std::env::var("OUT_DIR").unwrap();
-    let compressed: Vec<u8> =
-        zstd::stream::encode_all(archive.into_inner().unwrap().as_slice(), 19).unwrap();
+    // Lets the server advertise which version of the assets it has.
+    println!(
+        "cargo:rustc-env=FMC_ASSETS_HASH={}",
+        blake3::hash(&archive).to_hex()
+    );
+
+    let compressed: Vec<u8> = zstd::stream::encode_all(archive.as_slice(), 19).unwrap();
     let dest_path = std::path::Path::new(&out_dir).join("assets.tar.zstd");
 
     std::fs::write(dest_path, compressed).unwrap();
@@ -55,7 +108,20 @@ fn walk_dir<P: AsRef<std::path::Path>>(dir: P) -> Vec<std::path::PathBuf> {
     files
 }
 
-fn get_asset_paths() -> Vec<PathBuf> {
+/// The packages that have assets along with the directory they are in, ordered from lowest to
+/// highest priority. When two packages have the same asset, the one with the highest priority is
+/// used.
+///
+/// The priority can be set by listing packages in the binary's Cargo.toml, from lowest to
+/// highest:
+/// ```toml
+/// [package.metadata.fmc]
+/// asset-priority = ["fmc_vanilla", "some_mod"]
+/// ```
+/// Packages that aren't listed have a lower priority than those that are, in the order they
+/// appear among the dependencies. The binary's own package has the highest priority unless it is
+/// listed.
+fn get_asset_paths() -> Vec<(String, PathBuf)> {
     // Find the directory where the manifest of the binary being built is.
     let mut binary_dir = PathBuf::from(std::env::var_os("OUT_DIR").unwrap());
     while !binary_dir.ends_with("target") {
@@ -64,6 +130,7 @@ fn get_asset_paths() -> Vec<PathBuf> {
     binary_dir.pop();
 
     let manifest_path = binary_dir.join("Cargo.toml");
+    println!("cargo:rerun-if-changed={}", manifest_path.display());
 
     let meta = cargo_metadata::MetadataCommand::new()
         .cargo_path(std::env::var_os("CARGO").unwrap())
@@ -79,7 +146,7 @@ fn get_asset_paths() -> Vec<PathBuf> {
     }
 
     // A bit of mangling necessary here because the dependencies returned in metadata are in
-    // alphabetical order. The dependency order in Cargo.toml is the asset priority order, so we
+    // alphabetical order. The dependency order in Cargo.toml is the default asset priority, so we
     // have to build it independently. The 'toml' crate has a 'preserve order' feature we use.
     let mut asset_paths_unsorted = HashMap::new();
 
@@ -99,10 +166,6 @@ fn get_asset_paths() -> Vec<PathBuf> {
 
     let mut asset_paths = Vec::new();
 
-    // TODO: Currently using the order dependencies appear in Cargo.toml to decide asset
-    // presedence. This should not be left to the user. Sort mod assets into their own separate
-    // directories instead of overwriting. Make some resolution mechanism at runtime the
-    // mods can hook into to prioritize themselves.
     let manifest =
         toml::from_str::<toml::Table>(&std::fs::read_to_string(&manifest_path).unwrap()).unwrap();
     for (name, data) in manifest
@@ -114,13 +177,46 @@ fn get_asset_paths() -> Vec<PathBuf> {
         // Dependencies can be renamed by specifying the crate name in the 'package' field
         let name = data.get("package").and_then(|p| p.as_str()).unwrap_or(name);
 
-        asset_paths.push(asset_paths_unsorted.remove(name).unwrap());
+        asset_paths.push((name.to_owned(), asset_paths_unsorted.remove(name).unwrap()));
     }
 
     // Include assets from the binary's directory. For when you build the the library
     // crate as a binary or are creating a mod, where they wouldn't be counted among the
     // dependencies.
-    asset_paths.push(binary_dir);
+    asset_paths.push((root_package.name.clone(), binary_dir));
+
+    let priority: Vec<&str> = root_package
+        .metadata
+        .pointer("/fmc/asset-priority")
+        .and_then(|list| list.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|name| name.as_str())
+        .collect();
+
+    for name in priority.iter() {
+        if !asset_paths
+            .iter()
+            .any(|(package_name, _)| package_name == *name)
+        {
+            println!(
+                "cargo:warning='{}' is listed in 'asset-priority', but is not a dependency",
+                name
+            );
+        }
+    }
+
+    // TODO: Sort mod assets into their own separate directories instead of overwriting. Make
+    // some resolution mechanism at runtime the mods can hook into to prioritize themselves.
+    //
+    // The sort is stable, so packages that aren't listed keep the dependency order.
+    asset_paths.sort_by_key(
+        |(name, _)| match priority.iter().position(|listed| listed == name) {
+            Some(position) => position + 1,
+            None if *name == root_package.name => usize::MAX,
+            None => 0,
+        },
+    );
 
     return asset_paths;
 }
@@ -0,0 +1,273 @@
+use std::{
+    collections::HashMap,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use fmc::prelude::*;
+
+mod validation;
+
+/// Files in this directory are layered on top of the bundled assets, replacing the files with the
+/// same path, e.g. 'overrides/assets/client/textures/dirt.png' replaces
+/// 'assets/client/textures/dirt.png'.
+const OVERRIDES_PATH: &str = "./overrides/assets";
+/// Hashes of the assets as they were when they were extracted, used to tell which files have
+/// been edited by the user.
+const MANIFEST_PATH: &str = "./assets.manifest";
+/// Record of the overrides that were copied into the assets, so the bundled files can be
+/// restored when an override is removed.
+const APPLIED_OVERRIDES_PATH: &str = "./overrides/.applied";
+
+/// Hash of the bundled assets, it changes whenever any of the assets do.
+pub(crate) const ASSETS_HASH: &str = env!("FMC_ASSETS_HASH");
+/// Each bundled asset and the package it is from, one per line.
+pub(crate) const ASSET_ORIGINS: &str = include_str!(concat!(env!("OUT_DIR"), "/asset_origins.txt"));
+
+pub struct ExtractBundledAssetsPlugin;
+impl Plugin for ExtractBundledAssetsPlugin {
+    fn build(&self, app: &mut App) {
+        // Assets from both the game(this crate) + mods are included at compile time
+        // through the build script.
+        // 1. The assets are always available without having to fetch them from the web.
+        // 2. We do not need to have a list of necessary blocks/items/models included in the
+        //    source. Although if compiled without the required assets, it will cause runtime
+        //    errors.
+        let assets = include_bytes!(concat!(env!("OUT_DIR"), "/assets.tar.zstd"));
+        let uncompressed = zstd::stream::decode_all(assets.as_slice()).unwrap();
+        let mut archive = tar::Archive::new(uncompressed.as_slice());
+
+        // TODO: Want to store assets the same place they are unpacked so we don't have to
+        // contantly remove + write. This works fine for base game, but not for mods, as it would
+        // unpack all the server assets into the mod's assets directory.
+
+        // Overridden files are removed so they are extracted again, in case the override is gone.
+        remove_applied_overrides();
+
+        let mut log = Vec::new();
+        let manifest = extract(&mut archive, &mut log);
+        apply_overrides(&manifest, &mut log);
+
+        // The bundled assets are validated when they are built, but overrides and edits to the
+        // extracted files can break them.
+        let errors = validation::validate(&walk_dir("assets"), |path| {
+            std::fs::read_to_string(path).ok()
+        });
+        if !errors.is_empty() {
+            panic!(
+                "The assets refer to files, blocks or items that don't exist. Check the files \
+                in '{}' and any edits made to the extracted assets:\n{}",
+                OVERRIDES_PATH,
+                validation::format_errors(&errors)
+            );
+        }
+
+        // Logging isn't set up until after this plugin is built, so the log has to wait.
+        app.add_systems(Startup, move || {
+            info!("Asset version: {}", ASSETS_HASH);
+            for line in log.iter() {
+                match line {
+                    Ok(line) => info!("{}", line),
+                    Err(line) => warn!("{}", line),
+                }
+            }
+        });
+    }
+}
+
+/// Hashes of the extracted assets, one line per file, the hash followed by the path.
+#[derive(Default)]
+struct Manifest {
+    files: HashMap<PathBuf, blake3::Hash>,
+}
+
+impl Manifest {
+    fn load() -> Self {
+        let Ok(contents) = std::fs::read_to_string(MANIFEST_PATH) else {
+            return Self::default();
+        };
+
+        let files = contents
+            .lines()
+            .filter_map(|line| {
+                let (hash, path) = line.split_once(' ')?;
+                Some((PathBuf::from(path), blake3::Hash::from_hex(hash).ok()?))
+            })
+            .collect();
+
+        Self { files }
+    }
+
+    fn save(&self) {
+        let mut contents = String::new();
+        for (path, hash) in self.files.iter() {
+            contents += &format!("{} {}\n", hash.to_hex(), path.display());
+        }
+        std::fs::write(MANIFEST_PATH, contents).unwrap();
+    }
+}
+
+fn hash_file(path: &Path) -> Option<blake3::Hash> {
+    std::fs::read(path)
+        .ok()
+        .map(|contents| blake3::hash(&contents))
+}
+
+/// Extract the bundled assets. Files the server extracted earlier are replaced if the bundled
+/// version changed, or removed if they are no longer bundled. Files that have been edited since
+/// they were extracted are left alone, files without a recorded hash are assumed to be unedited.
+/// Returns the manifest of the bundled assets.
+fn extract(archive: &mut tar::Archive<&[u8]>, log: &mut Vec<Result<String, String>>) -> Manifest {
+    // When developing the assets are edited at their source, so there are no user edits to keep.
+    let developing = std::env::var_os("CARGO").is_some();
+
+    let old_manifest = Manifest::load();
+    let mut new_manifest = Manifest::default();
+    let mut updated = 0;
+
+    for entry in archive.entries().unwrap() {
+        let mut file = entry.unwrap();
+        if !file.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = file.path().unwrap().into_owned();
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).unwrap();
+        let hash = blake3::hash(&contents);
+        new_manifest.files.insert(path.clone(), hash);
+
+        if let Some(current_hash) = hash_file(&path) {
+            if current_hash == hash {
+                continue;
+            }
+
+            // Files missing from the manifest were extracted before there was one, the server
+            // overwrote them on every start back then, so they can't have been edited.
+            let old_hash = old_manifest.files.get(&path);
+            let edited = !developing && old_hash.is_some_and(|old_hash| *old_hash != current_hash);
+            if edited {
+                // Only warned about once for each new version of the file.
+                if old_hash != Some(&hash) {
+                    log.push(Err(format!(
+                        "The asset '{}' has been edited, so it was not updated to the new \
+                        version. Delete it to get the new version, or put your version in '{}' \
+                        to keep it.",
+                        path.display(),
+                        OVERRIDES_PATH
+                    )));
+                }
+                continue;
+            }
+
+            updated += 1;
+        }
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).unwrap();
+        }
+        if let Err(e) = std::fs::write(&path, contents) {
+            panic!("Failed to extract default assets.\nError: {e}");
+        }
+    }
+
+    let mut removed = 0;
+    for (path, old_hash) in old_manifest.files.iter() {
+        if new_manifest.files.contains_key(path) {
+            continue;
+        }
+
+        match hash_file(path) {
+            Some(current_hash) if developing || current_hash == *old_hash => {
+                std::fs::remove_file(path).ok();
+                removed += 1;
+            }
+            Some(_) => log.push(Err(format!(
+                "The asset '{}' is no longer used, but has been edited so it was not removed",
+                path.display()
+            ))),
+            None => (),
+        }
+    }
+
+    if updated > 0 || removed > 0 {
+        log.push(Ok(format!(
+            "Updated {} and removed {} outdated assets",
+            updated, removed
+        )));
+    }
+
+    new_manifest.save();
+
+    return new_manifest;
+}
+
+fn remove_applied_overrides() {
+    let Ok(applied) = std::fs::read_to_string(APPLIED_OVERRIDES_PATH) else {
+        return;
+    };
+
+    for path in applied.lines() {
+        std::fs::remove_file(path).ok();
+    }
+
+    std::fs::remove_file(APPLIED_OVERRIDES_PATH).ok();
+}
+
+/// Copy the overrides into the assets directory.
+fn apply_overrides(manifest: &Manifest, log: &mut Vec<Result<String, String>>) {
+    // Created so server owners can find it.
+    std::fs::create_dir_all(OVERRIDES_PATH).ok();
+
+    let mut applied = String::new();
+
+    for override_path in walk_dir(OVERRIDES_PATH) {
+        let relative_path = override_path.strip_prefix(OVERRIDES_PATH).unwrap();
+        let asset_path = Path::new("assets").join(relative_path);
+
+        if let Some(parent) = asset_path.parent() {
+            std::fs::create_dir_all(parent).unwrap();
+        }
+
+        if let Err(e) = std::fs::copy(&override_path, &asset_path) {
+            log.push(Err(format!(
+                "Failed to apply asset override '{}': {}",
+                override_path.display(),
+                e
+            )));
+            continue;
+        }
+
+        if manifest.files.contains_key(&asset_path) {
+            log.push(Ok(format!("Asset overridden: {}", asset_path.display())));
+        } else {
+            log.push(Ok(format!("Asset added: {}", asset_path.display())));
+        }
+
+        applied += &format!("{}\n", asset_path.display());
+    }
+
+    if !applied.is_empty() {
+        std::fs::write(APPLIED_OVERRIDES_PATH, applied).unwrap();
+    }
+}
+
+fn walk_dir<P: AsRef<Path>>(dir: P) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    let Ok(directory) = std::fs::read_dir(&dir) else {
+        return files;
+    };
+
+    for entry in directory {
+        let file_path = entry.unwrap().path();
+
+        if file_path.is_dir() {
+            files.extend(walk_dir(&file_path));
+        } else {
+            files.push(file_path);
+        }
+    }
+
+    files
+}
@@ -0,0 +1,298 @@
+//! Checks that the references between asset files point to something that exists, e.g. that the
+//! item a block drops has a configuration. Used by the build script on the bundled assets, and at
+//! startup on the extracted assets, as they may have been changed by overrides.
+//!
+//! This file is included by the build script, so it can only use std and serde_json.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+
+const BLOCKS: &str = "assets/client/blocks";
+const ITEMS: &str = "assets/client/items/configurations";
+const MODELS: &str = "assets/client/textures/models";
+const BLUEPRINTS: &str = "assets/server/blueprints";
+const INTERFACES: &str = "assets/client/interfaces";
+const MATERIALS: &str = "assets/client/materials";
+const BLOCK_TEXTURES: &str = "assets/client/textures/blocks";
+const ITEM_TEXTURES: &str = "assets/client/textures/items";
+const INTERFACE_TEXTURES: &str = "assets/client/textures/interfaces";
+const AUDIO: &str = "assets/client/audio";
+
+/// Check the references in all block, item, model, blueprint and interface configs. The paths
+/// are relative to the directory the 'assets' directory is in, and `read` returns the content of
+/// one of them. Returns the dangling references, grouped by the file they are in.
+pub fn validate(
+    paths: &[PathBuf],
+    read: impl Fn(&Path) -> Option<String>,
+) -> BTreeMap<PathBuf, Vec<String>> {
+    let files: HashSet<&Path> = paths.iter().map(PathBuf::as_path).collect();
+
+    let mut configs = Vec::new();
+    let mut errors: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+
+    for path in paths {
+        if path.extension().is_none_or(|extension| extension != "json") {
+            continue;
+        }
+
+        let Some(contents) = read(path) else {
+            continue;
+        };
+
+        match serde_json::from_str::<Value>(&contents) {
+            Ok(json) => configs.push((path, json)),
+            // Only the configs that are checked for references have to be valid json.
+            Err(error)
+                if [BLOCKS, ITEMS, MODELS, BLUEPRINTS, INTERFACES]
+                    .iter()
+                    .any(|directory| path.starts_with(directory)) =>
+            {
+                errors
+                    .entry(path.clone())
+                    .or_default()
+                    .push(format!("invalid json: {}", error));
+            }
+            Err(_) => continue,
+        }
+    }
+
+    let mut validator = Validator {
+        files,
+        blocks: HashSet::new(),
+        items: HashSet::new(),
+        blueprints: HashSet::new(),
+        errors: Vec::new(),
+    };
+
+    for (path, json) in configs.iter() {
+        if path.starts_with(BLOCKS) {
+            if let Some(name) = json.get("name").and_then(Value::as_str) {
+                validator.blocks.insert(name.to_owned());
+            }
+        } else if path.starts_with(ITEMS) {
+            validator.items.insert(file_stem(path));
+        } else if path.starts_with(BLUEPRINTS) {
+            validator.blueprints.insert(file_stem(path));
+        }
+    }
+
+    for (path, json) in configs.iter() {
+        if path.starts_with(BLOCKS) {
+            validator.check_block(path, json);
+        } else if path.starts_with(ITEMS) {
+            validator.check_item(json);
+        } else if path.starts_with(MODELS) {
+            validator.check_model(json);
+        } else if path.starts_with(BLUEPRINTS) {
+            validator.check_blueprint(json);
+        } else if path.starts_with(INTERFACES) {
+            validator.check_interface(json);
+        }
+
+        if !validator.errors.is_empty() {
+            errors
+                .entry(path.to_path_buf())
+                .or_default()
+                .append(&mut validator.errors);
+        }
+    }
+
+    errors
+}
+
+/// Format the errors returned by [validate] as one line per dangling reference.
+pub fn format_errors(errors: &BTreeMap<PathBuf, Vec<String>>) -> String {
+    let mut text = String::new();
+    for (path, file_errors) in errors {
+        for error in file_errors {
+            text += &format!("{}: {}\n", path.display(), error);
+        }
+    }
+    text
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem().unwrap().to_string_lossy().into_owned()
+}
+
+struct Validator<'a> {
+    files: HashSet<&'a Path>,
+    blocks: HashSet<String>,
+    items: HashSet<String>,
+    blueprints: HashSet<String>,
+    /// Errors for the file currently being checked
+    errors: Vec<String>,
+}
+
+impl Validator<'_> {
+    fn check_file(&mut self, field: &str, directory: &Path, name: &str) {
+        if !self.files.contains(directory.join(name).as_path()) {
+            self.errors.push(format!(
+                "'{}' refers to '{}', but there is no such file in '{}'",
+                field,
+                name,
+                directory.display()
+            ));
+        }
+    }
+
+    fn check_block_name(&mut self, field: &str, name: &str) {
+        if !self.blocks.contains(name) {
+            self.errors.push(format!(
+                "'{}' refers to the unknown block '{}'",
+                field, name
+            ));
+        }
+    }
+
+    fn check_item_name(&mut self, field: &str, name: &str) {
+        if !self.items.contains(name) {
+            self.errors
+                .push(format!("'{}' refers to the unknown item '{}'", field, name));
+        }
+    }
+
+    fn check_block(&mut self, path: &Path, json: &Value) {
+        if let Some(parent) = json.get("parent").and_then(Value::as_str) {
+            self.check_file("parent", path.parent().unwrap(), parent);
+        }
+
+        if let Some(material) = json.get("material").and_then(Value::as_str) {
+            self.check_file(
+                "material",
+                Path::new(MATERIALS),
+                &format!("{material}.json"),
+            );
+        }
+
+        if let Some(faces) = json.get("faces").and_then(Value::as_object) {
+            for (face, texture) in faces {
+                if let Some(texture) = texture.as_str() {
+                    self.check_file(&format!("faces.{face}"), Path::new(BLOCK_TEXTURES), texture);
+                }
+            }
+        }
+
+        for quad in json
+            .get("quads")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            if let Some(texture) = quad.get("texture").and_then(Value::as_str) {
+                self.check_file("quads.texture", Path::new(BLOCK_TEXTURES), texture);
+            }
+        }
+
+        if let Some(sounds) = json.get("sound").and_then(Value::as_object) {
+            for (kind, sounds) in sounds {
+                for sound in strings(sounds) {
+                    self.check_file(&format!("sound.{kind}"), Path::new(AUDIO), sound);
+                }
+            }
+        }
+
+        if let Some(drop) = json.get("drop") {
+            // Either the name of the item, or an object that has it in its own 'drop' field.
+            let drop = drop.get("drop").unwrap_or(drop);
+            for item in strings(drop) {
+                self.check_item_name("drop", item);
+            }
+        }
+    }
+
+    fn check_item(&mut self, json: &Value) {
+        if let Some(image) = json.get("image").and_then(Value::as_str) {
+            self.check_file("image", Path::new(ITEM_TEXTURES), image);
+        }
+
+        if let Some(block) = json.get("block").and_then(Value::as_str) {
+            self.check_block_name("block", block);
+        }
+
+        if let Some(model) = json.get("equip_model").and_then(Value::as_str) {
+            let models = Path::new(MODELS);
+            let exists = ["json", "glb", "gltf"].iter().any(|extension| {
+                self.files
+                    .contains(models.join(format!("{model}.{extension}")).as_path())
+            });
+            if !exists {
+                self.errors.push(format!(
+                    "'equip_model' refers to the unknown model '{}'",
+                    model
+                ));
+            }
+        }
+    }
+
+    fn check_model(&mut self, json: &Value) {
+        if let Some(faces) = json.get("block").and_then(Value::as_object) {
+            for (face, texture) in faces {
+                if let Some(texture) = texture.as_str() {
+                    self.check_file(&format!("block.{face}"), Path::new(BLOCK_TEXTURES), texture);
+                }
+            }
+        }
+    }
+
+    fn check_blueprint(&mut self, json: &Value) {
+        let Some(fields) = json.as_object() else {
+            return;
+        };
+
+        for (field, value) in fields {
+            if field == "blueprint" {
+                for blueprint in strings(value) {
+                    if !self.blueprints.contains(blueprint) {
+                        self.errors.push(format!(
+                            "'blueprint' refers to the unknown blueprint '{}'",
+                            blueprint
+                        ));
+                    }
+                }
+            } else if field.ends_with("_block")
+                || field.ends_with("_blocks")
+                || field == "can_replace"
+            {
+                for block in strings(value) {
+                    self.check_block_name(field, block);
+                }
+            }
+        }
+    }
+
+    fn check_interface(&mut self, json: &Value) {
+        match json {
+            Value::Object(fields) => {
+                for (field, value) in fields {
+                    if field == "image" {
+                        if let Some(image) = value.as_str() {
+                            self.check_file("image", Path::new(INTERFACE_TEXTURES), image);
+                        }
+                    } else {
+                        self.check_interface(value);
+                    }
+                }
+            }
+            Value::Array(values) => {
+                for value in values {
+                    self.check_interface(value);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// A string, or the strings in an array.
+fn strings(value: &Value) -> impl Iterator<Item = &str> {
+    let values = match value {
+        Value::Array(values) => values.as_slice(),
+        value => std::slice::from_ref(value),
+    };
+    values.iter().filter_map(Value::as_str)
+}
@@ -0,0 +1,162 @@
+use fmc::{database::Database, prelude::*};
+
+use crate::{
+    commands::{
+        Argument, ChatCommand, CommandAppExt, CommandInvocation, CommandReply, Parameter,
+        PermissionLevel,
+    },
+    time::{format_duration, unix_time},
+};
+
+pub(super) struct ChatLogPlugin;
+impl Plugin for ChatLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ChatLogEntry>()
+            .add_command(
+                ChatCommand::new("chatlog")
+                    .usage([
+                        Parameter::word("player"),
+                        Parameter::integer("count").optional(),
+                    ])
+                    .permission(PermissionLevel::MODERATOR)
+                    .help("Show the most recent messages and commands sent by a player"),
+                chatlog_command,
+            )
+            .add_systems(Startup, setup)
+            .add_systems(PostUpdate, write_chat_log);
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum ChatLogKind {
+    Chat,
+    Whisper,
+    Command,
+}
+
+impl ChatLogKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Chat => "chat",
+            Self::Whisper => "whisper",
+            Self::Command => "command",
+        }
+    }
+}
+
+/// Send to record something in the chat log.
+#[derive(Event)]
+pub struct ChatLogEntry {
+    pub kind: ChatLogKind,
+    pub sender: String,
+    /// Receiver of whispers
+    pub recipient: Option<String>,
+    pub message: String,
+    /// Outcome of commands
+    pub result: Option<String>,
+}
+
+fn setup(database: Res<Database>) {
+    let conn = database.get_connection();
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS chat_log (
+            timestamp INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            sender TEXT NOT NULL,
+            recipient TEXT,
+            message TEXT NOT NULL,
+            result TEXT
+        );
+        CREATE INDEX IF NOT EXISTS chat_log_sender ON chat_log (sender);",
+    )
+    .unwrap();
+}
+
+fn write_chat_log(database: Res<Database>, mut entries: EventReader<ChatLogEntry>) {
+    if entries.is_empty() {
+        return;
+    }
+
+    let timestamp = unix_time();
+
+    let conn = database.get_connection();
+    let mut stmt = conn
+        .prepare(
+            "INSERT INTO chat_log (timestamp, kind, sender, recipient, message, result)
+            VALUES (?,?,?,?,?,?)",
+        )
+        .unwrap();
+
+    for entry in entries.read() {
+        stmt.execute(rusqlite::params![
+            timestamp,
+            entry.kind.as_str(),
+            entry.sender,
+            entry.recipient,
+            entry.message,
+            entry.result
+        ])
+        .unwrap();
+    }
+}
+
+fn chatlog_command(
+    In(invocation): In<CommandInvocation>,
+    database: Res<Database>,
+    mut replies: EventWriter<CommandReply>,
+) {
+    const MAX_COUNT: i64 = 100;
+
+    let (username, count) = match invocation.arguments.as_slice() {
+        [Argument::Word(username)] => (username, 10),
+        [Argument::Word(username), Argument::Integer(count)] => (username, *count),
+        _ => unreachable!(),
+    };
+    let count = count.clamp(1, MAX_COUNT);
+
+    let conn = database.get_connection();
+    let mut stmt = conn
+        .prepare(
+            "SELECT timestamp, kind, recipient, message, result FROM chat_log
+            WHERE sender = ? ORDER BY rowid DESC LIMIT ?",
+        )
+        .unwrap();
+
+    let rows = stmt
+        .query_map(rusqlite::params![username, count], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+            ))
+        })
+        .unwrap()
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+
+    if rows.is_empty() {
+        replies.send(CommandReply::new(
+            invocation.sender,
+            format!("Nothing has been logged for {}", username),
+        ));
+        return;
+    }
+
+    let now = unix_time();
+    let mut text = format!("Chat log of {}:", username);
+    // Selected newest first, but shown oldest first like the chat.
+    for (timestamp, kind, recipient, message, result) in rows.into_iter().rev() {
+        text += &format!("\n[{} ago] {}", format_duration(now - timestamp), kind);
+        if let Some(recipient) = recipient {
+            text += &format!(" to {}", recipient);
+        }
+        text += &format!(": {}", message);
+        if let Some(result) = result {
+            text += &format!(" ({})", result);
+        }
+    }
+
+    replies.send(CommandReply::new(invocation.sender, text));
+}
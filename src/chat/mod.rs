@@ -0,0 +1,503 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::{
+    commands::{
+        Argument, ChatCommand, CommandAppExt, CommandInvocation, CommandRegistry, CommandReply,
+        CommandRequest, Console, Parameter, PermissionLevel,
+    },
+    fmc::{
+        networking::{NetworkEvent, NetworkMessage, Server},
+        players::Player,
+        prelude::*,
+        protocol::messages,
+    },
+    players::{AdmitPlayers, Rejected},
+    settings::Settings,
+};
+
+mod log;
+mod moderation;
+
+pub use log::{ChatLogEntry, ChatLogKind};
+pub use moderation::ChatFilter;
+use moderation::{ChatRateLimit, Verdict};
+
+pub const CHAT_FONT_SIZE: f32 = 8.0;
+pub const CHAT_TEXT_COLOR: &str = "#ffffff";
+pub const CHAT_ERROR_COLOR: &str = "#ff5555";
+pub const CHAT_WHISPER_COLOR: &str = "#b0b0b0";
+/// Color of messages that mention the receiving player's username
+pub const CHAT_MENTION_COLOR: &str = "#ffff55";
+pub const CHAT_MOTD_COLOR: &str = "#55ffff";
+
+/// Name used when the console sends chat messages
+pub const CONSOLE_NAME: &str = "Server";
+
+pub struct ChatPlugin;
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_command(
+            ChatCommand::new("msg")
+                .usage([Parameter::player("player"), Parameter::text("message")])
+                .permission(PermissionLevel::PLAYER)
+                .help("Send a private message"),
+            msg_command,
+        )
+        .add_command(
+            ChatCommand::new("r")
+                .usage([Parameter::text("message")])
+                .permission(PermissionLevel::PLAYER)
+                .help("Reply to the last private message you received"),
+            reply_command,
+        )
+        .add_command(
+            ChatCommand::new("me")
+                .usage([Parameter::text("action")])
+                .permission(PermissionLevel::PLAYER)
+                .help("Describe what you are doing, e.g. '/me waves'"),
+            me_command,
+        )
+        .add_plugins(moderation::ModerationPlugin)
+        .add_plugins(log::ChatLogPlugin)
+        .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            (
+                handle_chat_messages,
+                send_connection_messages.after(AdmitPlayers),
+                send_chat_history.after(send_connection_messages),
+                resize_chat_history.run_if(resource_changed::<Settings>),
+            ),
+        );
+    }
+}
+
+/// The most recent lines of public chat, sent to players when they join.
+#[derive(Resource)]
+pub struct ChatHistory {
+    lines: VecDeque<HistoryLine>,
+    capacity: usize,
+    /// Players that have connected, but aren't ready to receive chat messages yet. They are sent
+    /// the history once they are, which includes everything that was said in the meantime.
+    pending: HashSet<Entity>,
+}
+
+struct HistoryLine {
+    text: String,
+    color: String,
+}
+
+impl ChatHistory {
+    fn new(capacity: usize) -> Self {
+        Self {
+            lines: VecDeque::with_capacity(capacity),
+            capacity,
+            pending: HashSet::new(),
+        }
+    }
+
+    pub fn push(&mut self, text: &str, color: &str) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.lines.len() == self.capacity {
+            self.lines.pop_front();
+        }
+
+        self.lines.push_back(HistoryLine {
+            text: text.to_owned(),
+            color: color.to_owned(),
+        });
+    }
+
+    fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.lines.len() > capacity {
+            self.lines.pop_front();
+        }
+    }
+
+    /// If the player is ready to be sent chat messages.
+    pub fn is_ready(&self, player_entity: Entity) -> bool {
+        !self.pending.contains(&player_entity)
+    }
+}
+
+fn setup(mut commands: Commands, settings: Res<Settings>) {
+    commands.insert_resource(ChatHistory::new(settings.chat_history_size));
+}
+
+fn resize_chat_history(settings: Res<Settings>, mut chat_history: ResMut<ChatHistory>) {
+    chat_history.set_capacity(settings.chat_history_size);
+}
+
+/// Who to answer when a player uses /r, set when they receive a private message.
+#[derive(Component)]
+struct ReplyTarget(Entity);
+
+/// Build a message that appends a line to the chat history.
+pub fn chat_line(text: impl Into<String>, color: &str) -> messages::InterfaceTextUpdate {
+    messages::InterfaceTextUpdate {
+        interface_path: "chat/history".to_owned(),
+        index: i32::MAX,
+        text: text.into(),
+        font_size: CHAT_FONT_SIZE,
+        color: color.to_owned(),
+    }
+}
+
+/// Send a line to all players and record it in the chat history. Players whose username is
+/// mentioned in the message part of the line get it highlighted.
+fn broadcast_chat_message(
+    net: &Server,
+    player_query: &Query<(Entity, &Player)>,
+    chat_history: &mut ChatHistory,
+    line: &str,
+    message: &str,
+) {
+    chat_history.push(line, CHAT_TEXT_COLOR);
+
+    for (player_entity, player) in player_query.iter() {
+        if !chat_history.is_ready(player_entity) {
+            continue;
+        }
+
+        let color = if mentions(message, &player.username) {
+            CHAT_MENTION_COLOR
+        } else {
+            CHAT_TEXT_COLOR
+        };
+        net.send_one(player_entity, chat_line(line, color));
+    }
+}
+
+/// If the username appears as a separate word in the text, with or without an '@' in front.
+fn mentions(text: &str, username: &str) -> bool {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .any(|word| word.eq_ignore_ascii_case(username))
+}
+
+fn sender_name<'a>(sender: Entity, player_query: &'a Query<(Entity, &Player)>) -> &'a str {
+    player_query
+        .get(sender)
+        .map(|(_, player)| player.username.as_str())
+        .unwrap_or(CONSOLE_NAME)
+}
+
+/// Send a line from the server to all players and record it in the chat history.
+pub fn broadcast_system_message(
+    net: &Server,
+    player_query: &Query<(Entity, &Player)>,
+    chat_history: &mut ChatHistory,
+    line: &str,
+) {
+    chat_history.push(line, CHAT_TEXT_COLOR);
+
+    for (player_entity, _) in player_query.iter() {
+        if chat_history.is_ready(player_entity) {
+            net.send_one(player_entity, chat_line(line, CHAT_TEXT_COLOR));
+        }
+    }
+}
+
+fn handle_chat_messages(
+    net: Res<Server>,
+    chat_filter: Res<ChatFilter>,
+    command_registry: Res<CommandRegistry>,
+    player_query: Query<(Entity, &Player)>,
+    // Players that weren't let in can't chat or use commands before they are disconnected.
+    mut rate_limit_query: Query<&mut ChatRateLimit, Without<Rejected>>,
+    mut chat_history: ResMut<ChatHistory>,
+    mut chat_message_query: EventReader<NetworkMessage<messages::InterfaceTextInput>>,
+    mut command_requests: EventWriter<CommandRequest>,
+    mut chat_log: EventWriter<ChatLogEntry>,
+) {
+    for chat_message in chat_message_query.read() {
+        if &chat_message.interface_path != "chat/input" {
+            continue;
+        }
+
+        let player_entity = chat_message.player_entity;
+
+        let (Ok((_, player)), Ok(mut rate_limit)) = (
+            player_query.get(player_entity),
+            rate_limit_query.get_mut(player_entity),
+        ) else {
+            // TODO: Should probably disconnect
+            continue;
+        };
+
+        // Key bindings can be pressed faster than anyone types.
+        if command_registry.is_input(&chat_message.text) {
+            command_requests.send(CommandRequest {
+                sender: player_entity,
+                text: chat_message.text.clone(),
+            });
+            continue;
+        }
+
+        let text = match moderation::moderate(&mut rate_limit, &chat_message.text) {
+            Verdict::Allow(text) => text,
+            Verdict::Reject(reason) => {
+                net.send_one(player_entity, chat_line(reason, CHAT_ERROR_COLOR));
+                continue;
+            }
+            Verdict::Disconnect => {
+                warn!("Disconnected {} for spamming chat", player.username);
+                net.disconnect(player_entity);
+                continue;
+            }
+        };
+
+        if text.is_empty() {
+            continue;
+        }
+
+        if text.starts_with("/") {
+            command_requests.send(CommandRequest {
+                sender: player_entity,
+                text,
+            });
+        } else {
+            // The log keeps what was actually said.
+            let censored = chat_filter.censor(&text);
+            broadcast_chat_message(
+                &net,
+                &player_query,
+                &mut chat_history,
+                &format!("[{}] {}", &player.username, &censored),
+                &censored,
+            );
+
+            chat_log.send(ChatLogEntry {
+                kind: ChatLogKind::Chat,
+                sender: player.username.clone(),
+                recipient: None,
+                message: text,
+                result: None,
+            });
+        }
+    }
+}
+
+/// Deliver a private message, returns the line that should be shown to the sender.
+fn whisper(
+    commands: &mut Commands,
+    net: &Server,
+    player_query: &Query<(Entity, &Player)>,
+    console_query: &Query<(), With<Console>>,
+    sender: Entity,
+    receiver: Entity,
+    message: &str,
+) -> String {
+    let from = sender_name(sender, player_query);
+    let to = sender_name(receiver, player_query);
+
+    let received = format!("[{} -> you] {}", from, message);
+    if console_query.contains(receiver) {
+        println!("{}", received);
+    } else {
+        net.send_one(receiver, chat_line(received, CHAT_WHISPER_COLOR));
+    }
+
+    commands.entity(receiver).insert(ReplyTarget(sender));
+
+    format!("[you -> {}] {}", to, message)
+}
+
+fn log_whisper(
+    chat_log: &mut EventWriter<ChatLogEntry>,
+    player_query: &Query<(Entity, &Player)>,
+    sender: Entity,
+    receiver: Entity,
+    message: &str,
+) {
+    chat_log.send(ChatLogEntry {
+        kind: ChatLogKind::Whisper,
+        sender: sender_name(sender, player_query).to_owned(),
+        recipient: Some(sender_name(receiver, player_query).to_owned()),
+        message: message.to_owned(),
+        result: None,
+    });
+}
+
+fn msg_command(
+    In(invocation): In<CommandInvocation>,
+    mut commands: Commands,
+    net: Res<Server>,
+    chat_filter: Res<ChatFilter>,
+    player_query: Query<(Entity, &Player)>,
+    console_query: Query<(), With<Console>>,
+    mut replies: EventWriter<CommandReply>,
+    mut chat_log: EventWriter<ChatLogEntry>,
+) {
+    let [Argument::Player(receiver), Argument::Text(message)] = invocation.arguments.as_slice()
+    else {
+        unreachable!()
+    };
+
+    let sent = whisper(
+        &mut commands,
+        &net,
+        &player_query,
+        &console_query,
+        invocation.sender,
+        *receiver,
+        &chat_filter.censor(message),
+    );
+    replies.send(CommandReply::new(invocation.sender, sent));
+
+    log_whisper(
+        &mut chat_log,
+        &player_query,
+        invocation.sender,
+        *receiver,
+        message,
+    );
+}
+
+fn reply_command(
+    In(invocation): In<CommandInvocation>,
+    mut commands: Commands,
+    net: Res<Server>,
+    chat_filter: Res<ChatFilter>,
+    player_query: Query<(Entity, &Player)>,
+    console_query: Query<(), With<Console>>,
+    reply_target_query: Query<&ReplyTarget>,
+    mut replies: EventWriter<CommandReply>,
+    mut chat_log: EventWriter<ChatLogEntry>,
+) {
+    let [Argument::Text(message)] = invocation.arguments.as_slice() else {
+        unreachable!()
+    };
+    // The player that sent the last message might have left since.
+    let Some(receiver) = reply_target_query
+        .get(invocation.sender)
+        .ok()
+        .map(|target| target.0)
+        .filter(|receiver| player_query.contains(*receiver) || console_query.contains(*receiver))
+    else {
+        replies.send(CommandReply::error(
+            invocation.sender,
+            "There is nobody to reply to",
+        ));
+        return;
+    };
+
+    let sent = whisper(
+        &mut commands,
+        &net,
+        &player_query,
+        &console_query,
+        invocation.sender,
+        receiver,
+        &chat_filter.censor(message),
+    );
+    replies.send(CommandReply::new(invocation.sender, sent));
+
+    log_whisper(
+        &mut chat_log,
+        &player_query,
+        invocation.sender,
+        receiver,
+        message,
+    );
+}
+
+fn me_command(
+    In(invocation): In<CommandInvocation>,
+    net: Res<Server>,
+    chat_filter: Res<ChatFilter>,
+    player_query: Query<(Entity, &Player)>,
+    mut chat_history: ResMut<ChatHistory>,
+) {
+    let [Argument::Text(action)] = invocation.arguments.as_slice() else {
+        unreachable!()
+    };
+    let action = chat_filter.censor(action);
+
+    let name = sender_name(invocation.sender, &player_query);
+    broadcast_chat_message(
+        &net,
+        &player_query,
+        &mut chat_history,
+        &format!("* {} {}", name, action),
+        &action,
+    );
+}
+
+// The join message is not sent directly to the player that joined, it is part of the history
+// they get once their client is ready. If sent right away it may arrive before the chat interface
+// exists.
+fn send_connection_messages(
+    net: Res<Server>,
+    player_query: Query<(Entity, &Player)>,
+    rejected_query: Query<(), With<Rejected>>,
+    mut chat_history: ResMut<ChatHistory>,
+    mut network_events: EventReader<NetworkEvent>,
+) {
+    for event in network_events.read() {
+        // Players that weren't allowed to join never really joined.
+        let entity = match event {
+            NetworkEvent::Connected { entity } | NetworkEvent::Disconnected { entity } => entity,
+        };
+        if rejected_query.contains(*entity) {
+            continue;
+        }
+
+        match event {
+            NetworkEvent::Connected { entity } => {
+                chat_history.pending.insert(*entity);
+
+                let (_, player) = player_query.get(*entity).unwrap();
+                broadcast_system_message(
+                    &net,
+                    &player_query,
+                    &mut chat_history,
+                    &format!("{} joined the game", player.username),
+                );
+            }
+            NetworkEvent::Disconnected { entity } => {
+                chat_history.pending.remove(entity);
+
+                let (_, player) = player_query.get(*entity).unwrap();
+                broadcast_system_message(
+                    &net,
+                    &player_query,
+                    &mut chat_history,
+                    &format!("{} left the game", player.username),
+                );
+            }
+        }
+    }
+}
+
+// The client starts reporting its position once it has finished loading, at which point the
+// chat interface is ready to receive the history. The message of the day is shown after it.
+fn send_chat_history(
+    net: Res<Server>,
+    settings: Res<Settings>,
+    mut chat_history: ResMut<ChatHistory>,
+    mut position_updates: EventReader<NetworkMessage<messages::PlayerPosition>>,
+) {
+    for position_update in position_updates.read() {
+        if !chat_history.pending.remove(&position_update.player_entity) {
+            continue;
+        }
+
+        for line in chat_history.lines.iter() {
+            net.send_one(
+                position_update.player_entity,
+                chat_line(line.text.clone(), &line.color),
+            );
+        }
+
+        for line in settings.motd.lines() {
+            net.send_one(
+                position_update.player_entity,
+                chat_line(line, CHAT_MOTD_COLOR),
+            );
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::{collections::HashSet, time::Instant};
+
+use fmc::{players::Player, prelude::*};
+
+/// Path of the file with words that should be censored, one word per line.
+const FILTER_PATH: &str = "./chat_filter.txt";
+
+/// Max number of characters in a message
+pub const MAX_MESSAGE_LENGTH: usize = 256;
+/// How many messages can be sent in quick succession before the rate limit kicks in.
+const BURST_SIZE: f32 = 5.0;
+/// How many messages per second can be sent over time.
+const REFILL_RATE: f32 = 1.0;
+/// Messages sent while rate limited before the player is muted.
+const VIOLATIONS_BEFORE_MUTE: u32 = 5;
+const MUTE_DURATION: std::time::Duration = std::time::Duration::from_secs(60);
+/// Times a player can be muted before they are disconnected instead.
+const MUTES_BEFORE_DISCONNECT: u32 = 3;
+
+pub(super) struct ModerationPlugin;
+impl Plugin for ModerationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ChatFilter::load())
+            .add_systems(Update, add_rate_limits);
+    }
+}
+
+/// Words that are replaced by asterisks in chat.
+#[derive(Resource, Default)]
+pub struct ChatFilter {
+    // Stored in lowercase, matching is case insensitive.
+    words: HashSet<String>,
+}
+
+impl ChatFilter {
+    fn load() -> Self {
+        let Ok(contents) = std::fs::read_to_string(FILTER_PATH) else {
+            return Self::default();
+        };
+
+        let words = contents
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with("#"))
+            .map(|word| word.to_lowercase())
+            .collect();
+
+        Self { words }
+    }
+
+    pub fn censor(&self, text: &str) -> String {
+        if self.words.is_empty() {
+            return text.to_owned();
+        }
+
+        let mut censored = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(c) = rest.chars().next() {
+            if !c.is_alphanumeric() {
+                censored.push(c);
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+
+            let end = rest
+                .find(|c: char| !c.is_alphanumeric())
+                .unwrap_or(rest.len());
+            let (word, remainder) = rest.split_at(end);
+
+            if self.words.contains(&word.to_lowercase()) {
+                censored.extend(std::iter::repeat('*').take(word.chars().count()));
+            } else {
+                censored.push_str(word);
+            }
+
+            rest = remainder;
+        }
+
+        censored
+    }
+}
+
+/// Token bucket that limits how often a player can send chat messages and commands.
+#[derive(Component)]
+pub(super) struct ChatRateLimit {
+    tokens: f32,
+    last_update: Instant,
+    violations: u32,
+    mutes: u32,
+    muted_until: Option<Instant>,
+}
+
+impl Default for ChatRateLimit {
+    fn default() -> Self {
+        Self {
+            tokens: BURST_SIZE,
+            last_update: Instant::now(),
+            violations: 0,
+            mutes: 0,
+            muted_until: None,
+        }
+    }
+}
+
+/// What should be done with a message
+pub(super) enum Verdict {
+    /// The message can be sent, with control characters removed. Filtered words are censored
+    /// later, only in what is shown to other players, so command names and arguments are kept.
+    Allow(String),
+    /// The message should be dropped and the player told why.
+    Reject(String),
+    /// The player keeps spamming after being muted.
+    Disconnect,
+}
+
+impl ChatRateLimit {
+    fn take_token(&mut self, now: Instant) -> Result<(), Verdict> {
+        if let Some(muted_until) = self.muted_until {
+            if now < muted_until {
+                return Err(Verdict::Reject(format!(
+                    "You are muted for {} more seconds",
+                    (muted_until - now).as_secs() + 1
+                )));
+            }
+            self.muted_until = None;
+        }
+
+        let elapsed = (now - self.last_update).as_secs_f32();
+        self.tokens = (self.tokens + elapsed * REFILL_RATE).min(BURST_SIZE);
+        self.last_update = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+
+        self.violations += 1;
+        if self.violations < VIOLATIONS_BEFORE_MUTE {
+            return Err(Verdict::Reject(
+                "You are sending messages too fast".to_owned(),
+            ));
+        }
+
+        self.violations = 0;
+        self.mutes += 1;
+        if self.mutes >= MUTES_BEFORE_DISCONNECT {
+            return Err(Verdict::Disconnect);
+        }
+
+        self.muted_until = Some(now + MUTE_DURATION);
+        Err(Verdict::Reject(format!(
+            "You have been muted for {} seconds for spamming",
+            MUTE_DURATION.as_secs()
+        )))
+    }
+}
+
+/// Check a message from a player against the rate limit and length limit.
+pub(super) fn moderate(rate_limit: &mut ChatRateLimit, text: &str) -> Verdict {
+    if let Err(verdict) = rate_limit.take_token(Instant::now()) {
+        return verdict;
+    }
+
+    let text: String = text.chars().filter(|c| !c.is_control()).collect();
+    let text = text.trim();
+
+    if text.chars().count() > MAX_MESSAGE_LENGTH {
+        return Verdict::Reject(format!(
+            "Messages can be at most {} characters long",
+            MAX_MESSAGE_LENGTH
+        ));
+    }
+
+    Verdict::Allow(text.to_owned())
+}
+
+fn add_rate_limits(mut commands: Commands, new_players: Query<Entity, Added<Player>>) {
+    for player_entity in new_players.iter() {
+        commands
+            .entity(player_entity)
+            .insert(ChatRateLimit::default());
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use fmc::{
+    bevy::math::DVec3,
+    blocks::{BlockId, Blocks},
+    items::{ItemId, Items},
+    prelude::*,
+};
+
+/// A single parameter of a command usage.
+#[derive(Clone)]
+pub struct Parameter {
+    name: &'static str,
+    kind: ParameterKind,
+    optional: bool,
+}
+
+#[derive(Clone)]
+enum ParameterKind {
+    Integer,
+    Word,
+    /// Consumes the rest of the input, must be the last parameter.
+    Text,
+    Player,
+    Block,
+    Item,
+    /// Three coordinates, each can be relative to the sender's position with the '~' prefix.
+    Position,
+    Choice(&'static [&'static str]),
+    Literal,
+}
+
+impl Parameter {
+    fn new(name: &'static str, kind: ParameterKind) -> Self {
+        Self {
+            name,
+            kind,
+            optional: false,
+        }
+    }
+
+    pub fn integer(name: &'static str) -> Self {
+        Self::new(name, ParameterKind::Integer)
+    }
+
+    pub fn word(name: &'static str) -> Self {
+        Self::new(name, ParameterKind::Word)
+    }
+
+    pub fn text(name: &'static str) -> Self {
+        Self::new(name, ParameterKind::Text)
+    }
+
+    pub fn player(name: &'static str) -> Self {
+        Self::new(name, ParameterKind::Player)
+    }
+
+    pub fn block(name: &'static str) -> Self {
+        Self::new(name, ParameterKind::Block)
+    }
+
+    pub fn item(name: &'static str) -> Self {
+        Self::new(name, ParameterKind::Item)
+    }
+
+    pub fn position(name: &'static str) -> Self {
+        Self::new(name, ParameterKind::Position)
+    }
+
+    /// One of several keywords, e.g. 'noon' or 'midnight'
+    pub fn choice(name: &'static str, choices: &'static [&'static str]) -> Self {
+        Self::new(name, ParameterKind::Choice(choices))
+    }
+
+    /// A keyword that must be typed out as is, used for subcommands.
+    pub fn literal(literal: &'static str) -> Self {
+        Self::new(literal, ParameterKind::Literal)
+    }
+
+    /// Optional parameters can be left out, but only from the end of the input.
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+}
+
+impl std::fmt::Display for Parameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self.kind {
+            ParameterKind::Literal => return write!(f, "{}", self.name),
+            ParameterKind::Choice(choices) => choices.join("|"),
+            ParameterKind::Position => "x y z".to_owned(),
+            _ => self.name.to_owned(),
+        };
+
+        if self.optional {
+            write!(f, "[{}]", name)
+        } else {
+            write!(f, "<{}>", name)
+        }
+    }
+}
+
+/// A parsed argument, there is one for each parameter that was present in the input.
+#[derive(Clone, Debug)]
+pub enum Argument {
+    Integer(i64),
+    /// A single word, produced by word, choice and literal parameters.
+    Word(String),
+    Text(String),
+    Player(Entity),
+    Block(BlockId),
+    Item(ItemId),
+    Position(DVec3),
+}
+
+/// The world state arguments are parsed against.
+pub(super) struct ParseContext<'a> {
+    /// Position of the command sender, used to resolve relative coordinates.
+    pub sender_position: Option<DVec3>,
+    /// Entities of the connected players by username.
+    pub players: &'a HashMap<String, Entity>,
+    pub items: &'a Items,
+}
+
+pub(super) struct ParseError {
+    /// How many parameters were successfully parsed before the error, used to pick the most
+    /// relevant error when there are several usages.
+    pub parsed: usize,
+    pub message: String,
+}
+
+pub(super) fn parse(
+    parameters: &[Parameter],
+    input: &str,
+    context: &ParseContext,
+) -> Result<Vec<Argument>, ParseError> {
+    let mut arguments = Vec::with_capacity(parameters.len());
+    let mut rest = input.trim_start();
+
+    for (index, parameter) in parameters.iter().enumerate() {
+        let error = |message: String| ParseError {
+            parsed: index,
+            message,
+        };
+
+        if rest.is_empty() {
+            if parameter.optional {
+                break;
+            }
+            return Err(error(format!("Missing argument {}", parameter)));
+        }
+
+        let argument = match parameter.kind {
+            ParameterKind::Text => {
+                let text = rest.trim_end().to_owned();
+                rest = "";
+                Argument::Text(text)
+            }
+            ParameterKind::Position => {
+                let mut coordinates = [0.0; 3];
+                for (axis, coordinate) in coordinates.iter_mut().enumerate() {
+                    let Some(word) = next_word(&mut rest) else {
+                        return Err(error(format!("Missing coordinates for {}", parameter)));
+                    };
+                    *coordinate = parse_coordinate(word, axis, context).map_err(error)?;
+                }
+                Argument::Position(DVec3::from_array(coordinates))
+            }
+            _ => {
+                let word = next_word(&mut rest).unwrap();
+                parse_word(parameter, word, context).map_err(error)?
+            }
+        };
+
+        arguments.push(argument);
+    }
+
+    if let Some(word) = next_word(&mut rest) {
+        return Err(ParseError {
+            parsed: arguments.len(),
+            message: format!("Unexpected argument '{}'", word),
+        });
+    }
+
+    Ok(arguments)
+}
+
+fn next_word<'a>(input: &mut &'a str) -> Option<&'a str> {
+    let trimmed = input.trim_start();
+    if trimmed.is_empty() {
+        *input = trimmed;
+        return None;
+    }
+
+    let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+    let (word, rest) = trimmed.split_at(end);
+    *input = rest;
+    Some(word)
+}
+
+fn parse_word(
+    parameter: &Parameter,
+    word: &str,
+    context: &ParseContext,
+) -> Result<Argument, String> {
+    let argument = match parameter.kind {
+        ParameterKind::Integer => match word.parse::<i64>() {
+            Ok(value) => Argument::Integer(value),
+            Err(_) => return Err(format!("'{}' is not a valid integer", word)),
+        },
+        ParameterKind::Word => Argument::Word(word.to_owned()),
+        ParameterKind::Choice(choices) => {
+            if !choices.contains(&word) {
+                return Err(format!(
+                    "'{}' is not valid, must be one of: {}",
+                    word,
+                    choices.join(", ")
+                ));
+            }
+            Argument::Word(word.to_owned())
+        }
+        ParameterKind::Literal => {
+            if word != parameter.name {
+                return Err(format!("Expected '{}', found '{}'", parameter.name, word));
+            }
+            Argument::Word(word.to_owned())
+        }
+        ParameterKind::Player => match context.players.get(word) {
+            Some(player_entity) => Argument::Player(*player_entity),
+            None => return Err(format!("No player named '{}' is online", word)),
+        },
+        ParameterKind::Block => match Blocks::get().get_ids().get(word) {
+            Some(block_id) => Argument::Block(*block_id),
+            None => return Err(format!("There is no block named '{}'", word)),
+        },
+        ParameterKind::Item => match context.items.get_id(word) {
+            Some(item_id) => Argument::Item(item_id),
+            None => return Err(format!("There is no item named '{}'", word)),
+        },
+        ParameterKind::Text | ParameterKind::Position => unreachable!(),
+    };
+
+    Ok(argument)
+}
+
+/// Coordinates are clamped to this. It keeps the block and chunk positions they are turned
+/// into far away from overflowing.
+const MAX_COORDINATE: f64 = 30_000_000.0;
+
+fn parse_coordinate(word: &str, axis: usize, context: &ParseContext) -> Result<f64, String> {
+    // Rust also parses 'inf' and 'NaN' as numbers.
+    let parse = |number: &str| {
+        number
+            .parse::<f64>()
+            .ok()
+            .filter(|number| number.is_finite())
+    };
+
+    let coordinate = match word.strip_prefix('~') {
+        None => parse(word).ok_or_else(|| format!("'{}' is not a valid coordinate", word))?,
+        Some(offset) => {
+            let Some(sender_position) = context.sender_position else {
+                return Err("Relative coordinates can only be used by players".to_owned());
+            };
+
+            let offset = if offset.is_empty() {
+                0.0
+            } else {
+                parse(offset)
+                    .ok_or_else(|| format!("'{}' is not a valid relative coordinate", word))?
+            };
+
+            sender_position[axis] + offset
+        }
+    };
+
+    Ok(coordinate.clamp(-MAX_COORDINATE, MAX_COORDINATE))
+}
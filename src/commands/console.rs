@@ -0,0 +1,109 @@
+use std::{
+    io::BufRead,
+    sync::{
+        mpsc::{Receiver, Sender},
+        Mutex,
+    },
+};
+
+use fmc::prelude::*;
+
+use super::{CommandRequest, PermissionLevel};
+
+/// Name of the named pipe commands are read from in addition to stdin. Create it in the server
+/// directory with 'mkfifo console' when the server runs without a terminal, e.g. under systemd.
+const CONSOLE_PIPE: &str = "./console";
+
+pub(super) struct ConsolePlugin;
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup)
+            .add_systems(Update, read_console_input);
+    }
+}
+
+/// Marker for the entity that sends commands typed into the server's terminal. Replies to it
+/// are printed to stdout.
+#[derive(Component)]
+pub struct Console;
+
+#[derive(Resource)]
+struct ConsoleInput(Mutex<Receiver<String>>);
+
+fn setup(mut commands: Commands) {
+    commands.spawn((Console, PermissionLevel::ADMIN));
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    commands.insert_resource(ConsoleInput(Mutex::new(receiver)));
+
+    let stdin_sender = sender.clone();
+    std::thread::spawn(move || {
+        read_lines(std::io::stdin().lock(), &stdin_sender);
+    });
+
+    #[cfg(unix)]
+    std::thread::spawn(move || {
+        use std::os::unix::fs::FileTypeExt;
+
+        let Ok(metadata) = std::fs::metadata(CONSOLE_PIPE) else {
+            return;
+        };
+
+        if !metadata.file_type().is_fifo() {
+            warn!(
+                "Found '{}', but it is not a named pipe, console commands will not be read from it",
+                CONSOLE_PIPE
+            );
+            return;
+        }
+
+        // Opening blocks until something opens the pipe for writing, and reading stops when the
+        // writer closes it. Reopen so the pipe can be written to any number of times.
+        loop {
+            let Ok(pipe) = std::fs::File::open(CONSOLE_PIPE) else {
+                return;
+            };
+
+            if !read_lines(std::io::BufReader::new(pipe), &sender) {
+                return;
+            }
+        }
+    });
+}
+
+/// Forward lines until the reader is exhausted. Returns false if the server has shut down.
+fn read_lines(reader: impl BufRead, sender: &Sender<String>) -> bool {
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if sender.send(line).is_err() {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn read_console_input(
+    console_input: Res<ConsoleInput>,
+    console_query: Query<Entity, With<Console>>,
+    mut command_requests: EventWriter<CommandRequest>,
+) {
+    let console_entity = console_query.single();
+    let receiver = console_input.0.lock().unwrap();
+
+    for line in receiver.try_iter() {
+        // The slash is optional when typing into the console
+        let text = line.trim();
+        command_requests.send(CommandRequest {
+            sender: console_entity,
+            text: text.to_owned(),
+        });
+    }
+}
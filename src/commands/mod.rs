@@ -0,0 +1,418 @@
+use std::collections::{BTreeMap, HashMap};
+
+use fmc::{
+    bevy::ecs::system::SystemId, items::Items, networking::Server, players::Player, prelude::*,
+};
+
+use crate::chat::{self, ChatLogEntry, ChatLogKind};
+
+mod arguments;
+mod console;
+mod permissions;
+
+pub use arguments::{Argument, Parameter};
+pub use console::Console;
+pub use permissions::PermissionLevel;
+
+pub struct CommandsPlugin;
+impl Plugin for CommandsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CommandRegistry>()
+            .add_event::<CommandRequest>()
+            .add_event::<CommandReply>()
+            .add_plugins(permissions::PermissionsPlugin)
+            .add_plugins(console::ConsolePlugin)
+            .add_command(
+                ChatCommand::new("help")
+                    .usage([Parameter::word("command").optional()])
+                    .permission(PermissionLevel::PLAYER)
+                    .help("List all commands, or show how to use one of them"),
+                help_command,
+            )
+            .add_systems(
+                Update,
+                (dispatch_commands, send_replies.after(dispatch_commands)),
+            );
+    }
+}
+
+/// A command that can be typed into chat as '/name arguments'.
+pub struct ChatCommand {
+    name: String,
+    /// Each usage is one way the command can be invoked. They are tried in order, and the first
+    /// one that parses is used.
+    usages: Vec<Vec<Parameter>>,
+    help: String,
+    permission: PermissionLevel,
+    /// If the command is sent by a key binding instead of being typed.
+    input: bool,
+}
+
+impl ChatCommand {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            usages: Vec::new(),
+            help: String::new(),
+            permission: PermissionLevel::OPERATOR,
+            input: false,
+        }
+    }
+
+    /// Add a way to invoke the command. Commands without any usages take no arguments.
+    pub fn usage(mut self, parameters: impl IntoIterator<Item = Parameter>) -> Self {
+        self.usages.push(parameters.into_iter().collect());
+        self
+    }
+
+    pub fn help(mut self, help: &str) -> Self {
+        self.help = help.to_owned();
+        self
+    }
+
+    /// The permission level needed to use the command, defaults to operator.
+    pub fn permission(mut self, permission: PermissionLevel) -> Self {
+        self.permission = permission;
+        self
+    }
+
+    /// Mark the command as one that is bound to a key in the client's 'commands.json'. It is
+    /// player input rather than chat, so it isn't held to the chat rate limit or written to the
+    /// chat log.
+    pub fn input(mut self) -> Self {
+        self.input = true;
+        self
+    }
+
+    fn usage_string(&self, usage: &[Parameter]) -> String {
+        let mut usage_string = format!("/{}", self.name);
+        for parameter in usage {
+            usage_string += &format!(" {}", parameter);
+        }
+        usage_string
+    }
+}
+
+/// The arguments a command handler is run with.
+#[derive(Clone, Debug)]
+pub struct CommandInvocation {
+    /// The player that sent the command, or the [Console] entity.
+    pub sender: Entity,
+    /// Arguments in the order of the usage that matched. Optional parameters that were left out
+    /// are not included.
+    pub arguments: Vec<Argument>,
+}
+
+struct RegisteredCommand {
+    command: ChatCommand,
+    handler: SystemId<In<CommandInvocation>>,
+}
+
+/// All commands that can be used, any plugin can add to it through [CommandAppExt::add_command].
+#[derive(Resource, Default)]
+pub struct CommandRegistry {
+    // Sorted so the help text is stable
+    commands: BTreeMap<String, RegisteredCommand>,
+}
+
+impl CommandRegistry {
+    pub fn contains(&self, name: &str) -> bool {
+        self.commands.contains_key(name)
+    }
+
+    /// If the text is a command that was marked with [ChatCommand::input].
+    pub fn is_input(&self, text: &str) -> bool {
+        let Some(text) = text.strip_prefix("/") else {
+            return false;
+        };
+        let name = text.split_whitespace().next().unwrap_or_default();
+
+        self.commands
+            .get(name)
+            .is_some_and(|registered| registered.command.input)
+    }
+}
+
+pub trait CommandAppExt {
+    /// Register a command and the system that handles it. The system is run once for each
+    /// time the command is successfully parsed.
+    fn add_command<M>(
+        &mut self,
+        command: ChatCommand,
+        handler: impl IntoSystem<In<CommandInvocation>, (), M> + 'static,
+    ) -> &mut Self;
+}
+
+impl CommandAppExt for App {
+    fn add_command<M>(
+        &mut self,
+        command: ChatCommand,
+        handler: impl IntoSystem<In<CommandInvocation>, (), M> + 'static,
+    ) -> &mut Self {
+        let mut command = command;
+        if command.usages.is_empty() {
+            command.usages.push(Vec::new());
+        }
+
+        let handler = self.register_system(handler);
+        let mut registry = self
+            .world_mut()
+            .get_resource_or_insert_with(CommandRegistry::default);
+
+        if registry.contains(&command.name) {
+            panic!("The command '/{}' was registered twice", command.name);
+        }
+
+        registry
+            .commands
+            .insert(command.name.clone(), RegisteredCommand { command, handler });
+
+        self
+    }
+}
+
+/// Request to run a command, the text is what was typed, including the leading '/'.
+#[derive(Event)]
+pub struct CommandRequest {
+    pub sender: Entity,
+    pub text: String,
+}
+
+/// Output of a command that should be shown to its sender.
+#[derive(Event)]
+pub struct CommandReply {
+    pub sender: Entity,
+    pub text: String,
+    pub is_error: bool,
+}
+
+impl CommandReply {
+    pub fn new(sender: Entity, text: impl Into<String>) -> Self {
+        Self {
+            sender,
+            text: text.into(),
+            is_error: false,
+        }
+    }
+
+    pub fn error(sender: Entity, text: impl Into<String>) -> Self {
+        Self {
+            sender,
+            text: text.into(),
+            is_error: true,
+        }
+    }
+}
+
+fn dispatch_commands(
+    mut commands: Commands,
+    registry: Res<CommandRegistry>,
+    items: Res<Items>,
+    player_query: Query<(Entity, &Player)>,
+    transform_query: Query<&Transform, With<Player>>,
+    permission_query: Query<&PermissionLevel>,
+    mut requests: EventReader<CommandRequest>,
+    mut replies: EventWriter<CommandReply>,
+    mut chat_log: EventWriter<ChatLogEntry>,
+) {
+    if requests.is_empty() {
+        return;
+    }
+
+    let players: HashMap<String, Entity> = player_query
+        .iter()
+        .map(|(entity, player)| (player.username.clone(), entity))
+        .collect();
+
+    for request in requests.read() {
+        let sender_name = player_query
+            .get(request.sender)
+            .map(|(_, player)| player.username.as_str())
+            .unwrap_or(chat::CONSOLE_NAME);
+        let is_input = registry.is_input(&request.text);
+        let log_entry = |result: &str| {
+            (!is_input).then(|| ChatLogEntry {
+                kind: ChatLogKind::Command,
+                sender: sender_name.to_owned(),
+                recipient: None,
+                message: request.text.clone(),
+                result: Some(result.to_owned()),
+            })
+        };
+        let mut log = |result: &str| {
+            if let Some(entry) = log_entry(result) {
+                chat_log.send(entry);
+            }
+        };
+
+        let text = request.text.strip_prefix("/").unwrap_or(&request.text);
+        let (name, input) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+
+        let Some(registered) = registry.commands.get(name) else {
+            log("unknown command");
+            replies.send(CommandReply::error(
+                request.sender,
+                format!(
+                    "Unknown command '/{}', use /help to list all commands",
+                    name
+                ),
+            ));
+            continue;
+        };
+
+        let permission_level = permission_query
+            .get(request.sender)
+            .copied()
+            .unwrap_or_default();
+        if permission_level < registered.command.permission {
+            log("permission denied");
+            warn!(
+                "{} tried to use '/{}' without permission",
+                sender_name, registered.command.name
+            );
+            replies.send(CommandReply::error(
+                request.sender,
+                format!("You don't have permission to use '/{}'", name),
+            ));
+            continue;
+        }
+
+        let context = arguments::ParseContext {
+            sender_position: transform_query
+                .get(request.sender)
+                .ok()
+                .map(|transform| transform.translation),
+            players: &players,
+            items: &items,
+        };
+
+        let mut best_error: Option<(usize, &[Parameter], String)> = None;
+        let mut parsed = None;
+        for usage in registered.command.usages.iter() {
+            match arguments::parse(usage, input, &context) {
+                Ok(arguments) => {
+                    parsed = Some(arguments);
+                    break;
+                }
+                Err(error) => {
+                    if best_error
+                        .as_ref()
+                        .is_none_or(|(parsed, ..)| error.parsed > *parsed)
+                    {
+                        best_error = Some((error.parsed, usage.as_slice(), error.message));
+                    }
+                }
+            }
+        }
+
+        if let Some(arguments) = parsed {
+            let log_entry = log_entry("ok");
+            let sender = request.sender;
+            let handler = registered.handler;
+
+            // Run in a command instead of through 'Commands::run_system_with_input' so the
+            // replies from the handler can be read right after, to log if it failed.
+            commands.queue(move |world: &mut World| {
+                let mut replies = world
+                    .resource::<Events<CommandReply>>()
+                    .get_cursor_current();
+
+                world
+                    .run_system_with_input(handler, CommandInvocation { sender, arguments })
+                    .ok();
+
+                let Some(mut entry) = log_entry else {
+                    return;
+                };
+
+                let error = replies
+                    .read(world.resource::<Events<CommandReply>>())
+                    .find(|reply| reply.sender == sender && reply.is_error)
+                    .map(|reply| reply.text.clone());
+                if let Some(error) = error {
+                    entry.result = Some(error);
+                }
+
+                world.send_event(entry);
+            });
+        } else if let Some((_, usage, message)) = best_error {
+            log(&message);
+            replies.send(CommandReply::error(
+                request.sender,
+                format!(
+                    "{}\nUsage: {}",
+                    message,
+                    registered.command.usage_string(usage)
+                ),
+            ));
+        }
+    }
+}
+
+fn send_replies(
+    net: Res<Server>,
+    console_query: Query<(), With<Console>>,
+    mut replies: EventReader<CommandReply>,
+) {
+    for reply in replies.read() {
+        if console_query.contains(reply.sender) {
+            println!("{}", reply.text);
+            continue;
+        }
+
+        let color = if reply.is_error {
+            chat::CHAT_ERROR_COLOR
+        } else {
+            chat::CHAT_TEXT_COLOR
+        };
+
+        for line in reply.text.lines() {
+            net.send_one(reply.sender, chat::chat_line(line, color));
+        }
+    }
+}
+
+fn help_command(
+    In(invocation): In<CommandInvocation>,
+    registry: Res<CommandRegistry>,
+    permission_query: Query<&PermissionLevel>,
+    mut replies: EventWriter<CommandReply>,
+) {
+    let permission_level = permission_query
+        .get(invocation.sender)
+        .copied()
+        .unwrap_or_default();
+
+    let text = match invocation.arguments.as_slice() {
+        [Argument::Word(name)] => {
+            let name = name.strip_prefix("/").unwrap_or(name);
+            let Some(registered) = registry.commands.get(name) else {
+                replies.send(CommandReply::error(
+                    invocation.sender,
+                    format!("Unknown command '/{}'", name),
+                ));
+                return;
+            };
+
+            let command = &registered.command;
+            let mut text = command.help.clone();
+            for usage in command.usages.iter() {
+                text += "\n";
+                text += &command.usage_string(usage);
+            }
+            text
+        }
+        _ => {
+            let mut text = "Commands:".to_owned();
+            for (name, registered) in registry.commands.iter() {
+                if registered.command.permission > permission_level {
+                    continue;
+                }
+                text += &format!("\n/{} - {}", name, registered.command.help);
+            }
+            text
+        }
+    };
+
+    replies.send(CommandReply::new(invocation.sender, text));
+}
@@ -0,0 +1,208 @@
+use fmc::{database::Database, players::Player, prelude::*};
+
+use super::{Argument, ChatCommand, CommandAppExt, CommandInvocation, CommandReply, Parameter};
+
+pub(super) struct PermissionsPlugin;
+impl Plugin for PermissionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_command(
+            ChatCommand::new("op")
+                .usage([
+                    Parameter::word("player"),
+                    Parameter::integer("level").optional(),
+                ])
+                .permission(PermissionLevel::OPERATOR)
+                .help("Give a player permission to use commands, defaults to operator level"),
+            op_command,
+        )
+        .add_command(
+            ChatCommand::new("deop")
+                .usage([Parameter::word("player")])
+                .permission(PermissionLevel::OPERATOR)
+                .help("Remove all permissions from a player"),
+            deop_command,
+        )
+        .add_systems(Startup, setup)
+        .add_systems(Update, load_permission_levels);
+    }
+}
+
+/// How much a player is trusted, each command requires a minimum level to be used.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PermissionLevel(pub u8);
+
+impl PermissionLevel {
+    /// Everyone that joins
+    pub const PLAYER: Self = Self(0);
+    /// Can manage other players, e.g. kick them.
+    pub const MODERATOR: Self = Self(1);
+    /// Can change the world and give out permissions up to their own level.
+    pub const OPERATOR: Self = Self(2);
+    /// Full access, the server console always runs at this level.
+    pub const ADMIN: Self = Self(3);
+
+    pub(crate) fn load(username: &str, database: &Database) -> Self {
+        let conn = database.get_connection();
+        let mut stmt = conn
+            .prepare("SELECT level FROM permissions WHERE name = ?")
+            .unwrap();
+
+        match stmt.query_row([username], |row| row.get::<_, u8>(0)) {
+            Ok(level) => Self(level),
+            Err(_) => Self::PLAYER,
+        }
+    }
+
+    fn save(&self, username: &str, database: &Database) {
+        let conn = database.get_connection();
+
+        if *self == Self::PLAYER {
+            conn.execute("DELETE FROM permissions WHERE name = ?", [username])
+                .unwrap();
+        } else {
+            conn.execute(
+                "INSERT OR REPLACE INTO permissions (name, level) VALUES (?,?)",
+                rusqlite::params![username, self.0],
+            )
+            .unwrap();
+        }
+    }
+}
+
+impl std::fmt::Display for PermissionLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::PLAYER => write!(f, "player"),
+            Self::MODERATOR => write!(f, "moderator"),
+            Self::OPERATOR => write!(f, "operator"),
+            Self::ADMIN => write!(f, "admin"),
+            Self(level) => write!(f, "level {}", level),
+        }
+    }
+}
+
+fn setup(database: Res<Database>) {
+    let conn = database.get_connection();
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS permissions (
+            name TEXT PRIMARY KEY,
+            level INTEGER NOT NULL
+        )",
+        [],
+    )
+    .unwrap();
+}
+
+fn load_permission_levels(
+    mut commands: Commands,
+    database: Res<Database>,
+    new_players: Query<(Entity, &Player), Added<Player>>,
+) {
+    for (player_entity, player) in new_players.iter() {
+        commands
+            .entity(player_entity)
+            .insert(PermissionLevel::load(&player.username, &database));
+    }
+}
+
+fn set_permission_level(
+    username: &str,
+    level: PermissionLevel,
+    database: &Database,
+    player_query: &mut Query<(Option<&Player>, &mut PermissionLevel)>,
+) {
+    level.save(username, database);
+
+    for (player, mut permission_level) in player_query.iter_mut() {
+        if player.is_some_and(|player| player.username == username) {
+            *permission_level = level;
+        }
+    }
+}
+
+fn op_command(
+    In(invocation): In<CommandInvocation>,
+    database: Res<Database>,
+    // Includes the console, it has a permission level without being a player.
+    mut player_query: Query<(Option<&Player>, &mut PermissionLevel)>,
+    mut replies: EventWriter<CommandReply>,
+) {
+    let (username, level) = match invocation.arguments.as_slice() {
+        [Argument::Word(username)] => (username, PermissionLevel::OPERATOR),
+        [Argument::Word(username), Argument::Integer(level)] => {
+            let Ok(level) = u8::try_from(*level) else {
+                replies.send(CommandReply::error(
+                    invocation.sender,
+                    format!("'{}' is not a valid permission level", level),
+                ));
+                return;
+            };
+            (username, PermissionLevel(level))
+        }
+        _ => unreachable!(),
+    };
+
+    let sender_level = player_query
+        .get(invocation.sender)
+        .map(|(_, level)| *level)
+        .unwrap_or_default();
+    if level > sender_level {
+        replies.send(CommandReply::error(
+            invocation.sender,
+            "You can't give out a higher permission level than your own",
+        ));
+        return;
+    }
+
+    if PermissionLevel::load(username, &database) > sender_level {
+        replies.send(CommandReply::error(
+            invocation.sender,
+            format!("{} has a higher permission level than you", username),
+        ));
+        return;
+    }
+
+    set_permission_level(username, level, &database, &mut player_query);
+
+    info!("{} was given the permission level '{}'", username, level);
+    replies.send(CommandReply::new(
+        invocation.sender,
+        format!("{} is now {}", username, level),
+    ));
+}
+
+fn deop_command(
+    In(invocation): In<CommandInvocation>,
+    database: Res<Database>,
+    mut player_query: Query<(Option<&Player>, &mut PermissionLevel)>,
+    mut replies: EventWriter<CommandReply>,
+) {
+    let [Argument::Word(username)] = invocation.arguments.as_slice() else {
+        unreachable!()
+    };
+
+    let sender_level = player_query
+        .get(invocation.sender)
+        .map(|(_, level)| *level)
+        .unwrap_or_default();
+    if PermissionLevel::load(username, &database) > sender_level {
+        replies.send(CommandReply::error(
+            invocation.sender,
+            format!("{} has a higher permission level than you", username),
+        ));
+        return;
+    }
+
+    set_permission_level(
+        username,
+        PermissionLevel::PLAYER,
+        &database,
+        &mut player_query,
+    );
+
+    info!("{} had their permissions removed", username);
+    replies.send(CommandReply::new(
+        invocation.sender,
+        format!("{} is no longer an operator", username),
+    ));
+}
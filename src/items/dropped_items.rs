@@ -1,20 +1,39 @@
+use std::{collections::HashSet, time::Duration};
+
 use fmc::{
     bevy::math::DVec3,
     items::{ItemStack, Items},
     models::{Model, ModelMap, Models},
+    networking::Server,
     physics::{Collider, Physics},
     prelude::*,
+    protocol::messages,
     utils::Rng,
-    world::chunk::ChunkPosition,
+    world::{chunk::ChunkPosition, ChunkSubscriptions},
 };
 
-use crate::players::Hotbar;
+use crate::{
+    players::{Hotbar, Inventory},
+    settings::Settings,
+};
 
 pub struct DroppedItemsPlugin;
 impl Plugin for DroppedItemsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, pick_up_items)
-            .add_systems(Update, manage_item_models.in_set(DropItems));
+        app.add_systems(
+            Update,
+            (
+                despawn_old_items,
+                merge_items.after(DropItems),
+                // The items despawned by the systems above have to be gone before the pickup
+                // systems insert components on them.
+                (tick_pickup_cooldown, attract_items, pick_up_items)
+                    .chain()
+                    .after(despawn_old_items)
+                    .after(merge_items),
+            ),
+        )
+        .add_systems(Update, manage_item_models.in_set(DropItems));
     }
 }
 
@@ -24,7 +43,7 @@ pub struct DropItems;
 
 // An item that is dropped on the ground.
 #[derive(Component, Deref, DerefMut)]
-#[require(Transform)]
+#[require(Transform, ItemAge)]
 pub struct DroppedItem(ItemStack);
 
 impl DroppedItem {
@@ -33,6 +52,113 @@ impl DroppedItem {
     }
 }
 
+/// How long an item has been on the ground. It is despawned when it gets older than the
+/// 'item-despawn-time' setting.
+#[derive(Component, Default)]
+struct ItemAge(Duration);
+
+/// Stops a dropped item from being picked up until it runs out. Given to items players throw, so
+/// they don't pick them straight back up.
+#[derive(Component)]
+pub struct PickupCooldown(Timer);
+
+impl Default for PickupCooldown {
+    fn default() -> Self {
+        Self(Timer::from_seconds(1.5, TimerMode::Once))
+    }
+}
+
+fn tick_pickup_cooldown(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut cooldown_query: Query<(Entity, &mut PickupCooldown)>,
+) {
+    for (entity, mut cooldown) in cooldown_query.iter_mut() {
+        if cooldown.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<PickupCooldown>();
+        }
+    }
+}
+
+fn despawn_old_items(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut item_query: Query<(Entity, &mut ItemAge)>,
+) {
+    let lifetime = Duration::from_secs(settings.item_despawn_time);
+
+    for (entity, mut age) in item_query.iter_mut() {
+        age.0 += time.delta();
+
+        if settings.item_despawn_time != 0 && age.0 > lifetime {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Items closer than this are merged into one stack.
+const MERGE_DISTANCE: f64 = 0.75;
+
+// Breaking a lot of blocks in one place leaves a pile of items that would each have to be sent to
+// the clients. They are merged as long as they fit in one stack.
+fn merge_items(
+    mut commands: Commands,
+    model_map: Res<ModelMap>,
+    moved_items: Query<
+        (Entity, &Transform),
+        (
+            With<DroppedItem>,
+            Changed<Transform>,
+            Without<BeingPickedUp>,
+        ),
+    >,
+    mut item_query: Query<(&mut DroppedItem, &Transform, &mut ItemAge), Without<BeingPickedUp>>,
+    mut merged: Local<HashSet<Entity>>,
+) {
+    merged.clear();
+
+    for (entity, transform) in moved_items.iter() {
+        if merged.contains(&entity) {
+            continue;
+        }
+
+        let chunk_position = ChunkPosition::from(transform.translation);
+        let Some(item_entities) = model_map.get_entities(&chunk_position) else {
+            continue;
+        };
+
+        for other_entity in item_entities.iter() {
+            if *other_entity == entity || merged.contains(other_entity) {
+                continue;
+            }
+
+            let Ok([item, other_item]) = item_query.get_many_mut([entity, *other_entity]) else {
+                continue;
+            };
+            let (mut dropped_item, transform, mut age) = item;
+            let (mut other_item, other_transform, other_age) = other_item;
+
+            if dropped_item.item() != other_item.item()
+                || dropped_item.remaining_capacity() < other_item.size()
+                || transform
+                    .translation
+                    .distance_squared(other_transform.translation)
+                    > MERGE_DISTANCE * MERGE_DISTANCE
+            {
+                continue;
+            }
+
+            other_item.transfer_to(&mut dropped_item, u32::MAX);
+            // The merged item shouldn't disappear sooner than the newest of them would have.
+            age.0 = age.0.min(other_age.0);
+
+            commands.entity(*other_entity).despawn();
+            merged.insert(*other_entity);
+        }
+    }
+}
+
 fn manage_item_models(
     mut commands: Commands,
     models: Res<Models>,
@@ -82,76 +208,164 @@ fn manage_item_models(
     }
 }
 
-fn pick_up_items(
+/// Items closer than this to a player fly towards them.
+const PICKUP_RADIUS: f64 = 2.5;
+/// How long it takes for an item to reach the player.
+const PICKUP_DURATION: f32 = 0.15;
+// TODO: There's no sound made for it, so a gravel sound is played at a higher pitch.
+const PICKUP_SOUND: &str = "gravel_1.ogg";
+
+/// An item on its way to the player that picked it up.
+#[derive(Component)]
+struct BeingPickedUp {
+    player_entity: Entity,
+    /// Where the item was when it started moving.
+    start: DVec3,
+    timer: Timer,
+}
+
+fn attract_items(
     mut commands: Commands,
     model_map: Res<ModelMap>,
-    mut players: Query<(&GlobalTransform, &mut Hotbar), Changed<GlobalTransform>>,
-    mut dropped_items: Query<(Entity, &mut DroppedItem, &Transform)>,
+    players: Query<(Entity, &GlobalTransform, &Hotbar, &Inventory)>,
+    dropped_items: Query<
+        (&DroppedItem, &Transform),
+        (Without<PickupCooldown>, Without<BeingPickedUp>),
+    >,
+    mut attracted: Local<HashSet<Entity>>,
 ) {
-    for (player_position, mut player_hotbar) in players.iter_mut() {
-        let chunk_position = ChunkPosition::from(player_position.translation());
-        let item_entities = match model_map.get_entities(&chunk_position) {
-            Some(e) => e,
-            None => continue,
-        };
+    // The components aren't inserted until the commands are applied, this stops two players
+    // from attracting the same item.
+    attracted.clear();
 
-        for item_entity in item_entities.iter() {
-            if let Ok((entity, mut dropped_item, transform)) = dropped_items.get_mut(*item_entity) {
-                if transform
-                    .translation
-                    .distance_squared(player_position.translation())
-                    < 2.0
-                {
-                    // First test that the item can be picked up. This is to avoid triggering
-                    // change detection for the hotbar. If detection is triggered, it will send
-                    // an interface update to the client. Can't pick up = spam
-                    let mut capacity = false;
-                    for item_stack in player_hotbar.iter() {
-                        if (item_stack.item() == dropped_item.item()
-                            && item_stack.remaining_capacity() != 0)
-                            || item_stack.is_empty()
-                        {
-                            capacity = true;
-                            break;
-                        }
-                    }
-                    if !capacity {
-                        break;
-                    }
+    for (player_entity, player_transform, hotbar, inventory) in players.iter() {
+        let player_position = player_transform.translation();
 
-                    // First try to fill item stacks that already have the item
-                    for item_stack in player_hotbar.iter_mut() {
-                        if item_stack.item() == dropped_item.item() {
-                            dropped_item.transfer_to(item_stack, u32::MAX);
-                        }
-
-                        if dropped_item.is_empty() {
-                            break;
-                        }
+        // The radius can reach into the neighbouring chunks, so all the chunks at its corners
+        // are searched.
+        let mut chunk_positions = Vec::with_capacity(8);
+        for x in [-1.0, 1.0] {
+            for y in [-1.0, 1.0] {
+                for z in [-1.0, 1.0] {
+                    let corner = player_position + DVec3::new(x, y, z) * PICKUP_RADIUS;
+                    let chunk_position = ChunkPosition::from(corner);
+                    if !chunk_positions.contains(&chunk_position) {
+                        chunk_positions.push(chunk_position);
                     }
+                }
+            }
+        }
 
-                    if dropped_item.is_empty() {
-                        commands.entity(entity).despawn();
-                        continue;
-                    }
+        for chunk_position in chunk_positions {
+            let Some(item_entities) = model_map.get_entities(&chunk_position) else {
+                continue;
+            };
 
-                    // Then go again and fill empty spots
-                    for item_stack in player_hotbar.iter_mut() {
-                        if item_stack.is_empty() {
-                            dropped_item.transfer_to(item_stack, u32::MAX);
-                        }
+            for item_entity in item_entities.iter() {
+                if attracted.contains(item_entity) {
+                    continue;
+                }
 
-                        if dropped_item.is_empty() {
-                            break;
-                        }
-                    }
+                let Ok((dropped_item, transform)) = dropped_items.get(*item_entity) else {
+                    continue;
+                };
 
-                    if dropped_item.is_empty() {
-                        commands.entity(entity).despawn();
-                        continue;
-                    }
+                if transform.translation.distance_squared(player_position)
+                    > PICKUP_RADIUS * PICKUP_RADIUS
+                {
+                    continue;
                 }
+
+                if !hotbar.has_room_for(dropped_item) && !inventory.has_room_for(dropped_item) {
+                    continue;
+                }
+
+                // Physics would pull it back down while it's flying.
+                commands
+                    .entity(*item_entity)
+                    .remove::<Physics>()
+                    .try_insert(BeingPickedUp {
+                        player_entity,
+                        start: transform.translation,
+                        timer: Timer::from_seconds(PICKUP_DURATION, TimerMode::Once),
+                    });
+                attracted.insert(*item_entity);
             }
         }
     }
 }
+
+fn pick_up_items(
+    mut commands: Commands,
+    time: Res<Time>,
+    net: Res<Server>,
+    chunk_subscriptions: Res<ChunkSubscriptions>,
+    mut players: Query<(&GlobalTransform, &mut Hotbar, &mut Inventory)>,
+    mut dropped_items: Query<(Entity, &mut DroppedItem, &mut Transform, &mut BeingPickedUp)>,
+) {
+    for (entity, mut dropped_item, mut transform, mut pickup) in dropped_items.iter_mut() {
+        let Ok((player_transform, mut hotbar, mut inventory)) =
+            players.get_mut(pickup.player_entity)
+        else {
+            // The player left before it got to them.
+            commands
+                .entity(entity)
+                .remove::<BeingPickedUp>()
+                .try_insert(Physics {
+                    enabled: true,
+                    ..default()
+                });
+            continue;
+        };
+
+        pickup.timer.tick(time.delta());
+
+        // Aimed a little above the feet so it doesn't go through the ground.
+        let target = player_transform.translation() + DVec3::Y * 0.5;
+        transform.translation = pickup.start.lerp(target, pickup.timer.fraction() as f64);
+
+        if !pickup.timer.finished() {
+            continue;
+        }
+
+        let size_before = dropped_item.size();
+
+        // The hotbar is filled first, then the inventory. They're checked first to avoid
+        // triggering change detection, which would send an interface update to the client.
+        if hotbar.has_room_for(&dropped_item) {
+            hotbar.insert_stack(&mut dropped_item);
+        }
+
+        if inventory.has_room_for(&dropped_item) && !dropped_item.is_empty() {
+            inventory.insert_stack(&mut dropped_item);
+        }
+
+        if dropped_item.size() != size_before {
+            let chunk_position = ChunkPosition::from(target);
+            if let Some(subscribers) = chunk_subscriptions.get_subscribers(&chunk_position) {
+                net.send_many(
+                    subscribers,
+                    messages::Sound {
+                        position: Some(target),
+                        volume: 0.3,
+                        speed: 2.0,
+                        sound: PICKUP_SOUND.to_owned(),
+                    },
+                );
+            }
+        }
+
+        if dropped_item.is_empty() {
+            commands.entity(entity).despawn();
+        } else {
+            // The player ran out of room while it was on its way, it falls back down.
+            commands
+                .entity(entity)
+                .remove::<BeingPickedUp>()
+                .try_insert(Physics {
+                    enabled: true,
+                    ..default()
+                });
+        }
+    }
+}
@@ -4,7 +4,7 @@ use fmc::{items::ItemId, prelude::*};
 
 mod dropped_items;
 
-pub use dropped_items::DroppedItem;
+pub use dropped_items::{DroppedItem, PickupCooldown};
 
 pub struct ItemPlugin;
 impl Plugin for ItemPlugin {
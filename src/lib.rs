@@ -1,9 +1,12 @@
 mod assets;
 pub mod chat;
+pub mod commands;
 pub mod items;
 pub mod players;
 pub mod settings;
 pub mod skybox;
+pub mod status;
+mod time;
 pub mod world;
 
 pub use fmc;
@@ -21,10 +24,12 @@ impl PluginGroup for DefaultPlugins {
             .add(settings::SettingsPlugin)
             .add(assets::ExtractBundledAssetsPlugin)
             .add_group(fmc::DefaultPlugins)
+            .add(commands::CommandsPlugin)
             .add(items::ItemPlugin)
             .add(players::PlayerPlugin)
             .add(world::WorldPlugin)
             .add(skybox::SkyPlugin)
             .add(chat::ChatPlugin)
+            .add(status::StatusPlugin)
     }
 }
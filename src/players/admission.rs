@@ -0,0 +1,417 @@
+use fmc::{
+    database::Database, networking::Server, players::Player, prelude::*, protocol::messages,
+};
+
+use crate::{
+    commands::{
+        Argument, ChatCommand, CommandAppExt, CommandInvocation, CommandReply, Parameter,
+        PermissionLevel,
+    },
+    settings::Settings,
+    time::{format_duration, unix_time},
+};
+
+pub(super) struct AdmissionPlugin;
+impl Plugin for AdmissionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_command(
+            ChatCommand::new("whitelist")
+                .usage([Parameter::literal("add"), Parameter::word("player")])
+                .usage([Parameter::literal("remove"), Parameter::word("player")])
+                .usage([Parameter::literal("list")])
+                .help("Manage who can join when the whitelist is enabled in the settings"),
+            whitelist_command,
+        )
+        .add_command(
+            ChatCommand::new("ban")
+                .usage([
+                    Parameter::word("player"),
+                    Parameter::text("reason").optional(),
+                ])
+                .permission(PermissionLevel::MODERATOR)
+                .help("Ban a player from the server"),
+            ban_command,
+        )
+        .add_command(
+            ChatCommand::new("tempban")
+                .usage([
+                    Parameter::word("player"),
+                    Parameter::word("duration"),
+                    Parameter::text("reason").optional(),
+                ])
+                .permission(PermissionLevel::MODERATOR)
+                .help("Ban a player for a while, the duration is e.g. 30m, 12h or 7d"),
+            ban_command,
+        )
+        .add_command(
+            ChatCommand::new("unban")
+                .usage([Parameter::word("player")])
+                .permission(PermissionLevel::MODERATOR)
+                .help("Let a banned player join again"),
+            unban_command,
+        )
+        .add_command(
+            ChatCommand::new("kick")
+                .usage([
+                    Parameter::player("player"),
+                    Parameter::text("reason").optional(),
+                ])
+                .permission(PermissionLevel::MODERATOR)
+                .help("Disconnect a player from the server"),
+            kick_command,
+        )
+        .add_systems(Startup, setup)
+        .add_systems(Update, admit_players.in_set(AdmitPlayers));
+    }
+}
+
+/// Players are checked against the whitelist and ban list in this set. Systems that react to
+/// players joining should run after it and ignore those that are [Rejected].
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct AdmitPlayers;
+
+/// Marks a player that wasn't allowed to join, they are disconnected and never set up.
+#[derive(Component)]
+pub struct Rejected;
+
+fn setup(database: Res<Database>) {
+    let conn = database.get_connection();
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS whitelist (
+            name TEXT PRIMARY KEY
+        );
+        CREATE TABLE IF NOT EXISTS bans (
+            name TEXT PRIMARY KEY,
+            reason TEXT NOT NULL,
+            expires INTEGER
+        );",
+    )
+    .unwrap();
+}
+
+/// Parse durations like '30m', '12h' or '7d' into seconds.
+fn parse_duration(duration: &str) -> Option<i64> {
+    let unit = match duration.chars().last()? {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        _ => return None,
+    };
+    let amount: i64 = duration[..duration.len() - 1].parse().ok()?;
+
+    if amount <= 0 {
+        return None;
+    }
+
+    amount.checked_mul(unit)
+}
+
+struct Ban {
+    reason: String,
+    /// Unix time the ban runs out, permanent if None
+    expires: Option<i64>,
+}
+
+impl Ban {
+    /// Get the player's ban, if they are banned. Bans that have run out are removed.
+    fn load(username: &str, database: &Database) -> Option<Self> {
+        let conn = database.get_connection();
+        let mut stmt = conn
+            .prepare("SELECT reason, expires FROM bans WHERE name = ?")
+            .unwrap();
+
+        let ban = stmt
+            .query_row([username], |row| {
+                Ok(Ban {
+                    reason: row.get(0)?,
+                    expires: row.get(1)?,
+                })
+            })
+            .ok()?;
+
+        if ban.expires.is_some_and(|expires| expires <= unix_time()) {
+            conn.execute("DELETE FROM bans WHERE name = ?", [username])
+                .unwrap();
+            return None;
+        }
+
+        return Some(ban);
+    }
+
+    fn save(&self, username: &str, database: &Database) {
+        let conn = database.get_connection();
+        conn.execute(
+            "INSERT OR REPLACE INTO bans (name, reason, expires) VALUES (?,?,?)",
+            rusqlite::params![username, self.reason, self.expires],
+        )
+        .unwrap();
+    }
+
+    /// The reason shown to the player when they are disconnected.
+    fn message(&self) -> String {
+        let mut message = match self.expires {
+            Some(expires) => format!(
+                "You are banned from this server for another {}",
+                format_duration(expires - unix_time())
+            ),
+            None => "You are banned from this server".to_owned(),
+        };
+
+        if !self.reason.is_empty() {
+            message += &format!("\nReason: {}", self.reason);
+        }
+
+        message
+    }
+}
+
+fn is_whitelisted(username: &str, database: &Database) -> bool {
+    let conn = database.get_connection();
+    let mut stmt = conn
+        .prepare("SELECT 1 FROM whitelist WHERE name = ?")
+        .unwrap();
+    stmt.exists([username]).unwrap()
+}
+
+/// Disconnect a player, showing them why.
+pub fn disconnect_with_reason(net: &Server, player_entity: Entity, reason: &str) {
+    net.send_one(
+        player_entity,
+        messages::Disconnect {
+            message: reason.to_owned(),
+        },
+    );
+    net.disconnect(player_entity);
+}
+
+fn admit_players(
+    mut commands: Commands,
+    net: Res<Server>,
+    database: Res<Database>,
+    settings: Res<Settings>,
+    new_players: Query<(Entity, &Player), Added<Player>>,
+    admitted_players: Query<(), (With<Player>, Without<Rejected>)>,
+) {
+    let mut online = admitted_players.iter().count() - new_players.iter().count();
+
+    for (player_entity, player) in new_players.iter() {
+        let reason = if let Some(ban) = Ban::load(&player.username, &database) {
+            ban.message()
+        } else if settings.whitelist && !is_whitelisted(&player.username, &database) {
+            "You are not whitelisted on this server".to_owned()
+        } else if online >= settings.max_players {
+            "The server is full".to_owned()
+        } else {
+            online += 1;
+            continue;
+        };
+
+        info!("{} was refused entry: {}", player.username, reason);
+        commands.entity(player_entity).insert(Rejected);
+        disconnect_with_reason(&net, player_entity, &reason);
+    }
+}
+
+fn whitelist_command(
+    In(invocation): In<CommandInvocation>,
+    database: Res<Database>,
+    settings: Res<Settings>,
+    mut replies: EventWriter<CommandReply>,
+) {
+    let conn = database.get_connection();
+
+    let text = match invocation.arguments.as_slice() {
+        [Argument::Word(action), Argument::Word(username)] if action == "add" => {
+            conn.execute(
+                "INSERT OR IGNORE INTO whitelist (name) VALUES (?)",
+                [username],
+            )
+            .unwrap();
+            info!("{} was added to the whitelist", username);
+            format!("{} was added to the whitelist", username)
+        }
+        [Argument::Word(action), Argument::Word(username)] if action == "remove" => {
+            let removed = conn
+                .execute("DELETE FROM whitelist WHERE name = ?", [username])
+                .unwrap();
+            if removed == 0 {
+                replies.send(CommandReply::error(
+                    invocation.sender,
+                    format!("{} is not on the whitelist", username),
+                ));
+                return;
+            }
+            info!("{} was removed from the whitelist", username);
+            format!("{} was removed from the whitelist", username)
+        }
+        [Argument::Word(_)] => {
+            let mut stmt = conn
+                .prepare("SELECT name FROM whitelist ORDER BY name")
+                .unwrap();
+            let names = stmt
+                .query_map([], |row| row.get::<_, String>(0))
+                .unwrap()
+                .filter_map(Result::ok)
+                .collect::<Vec<_>>();
+
+            if names.is_empty() {
+                "The whitelist is empty".to_owned()
+            } else {
+                format!("Whitelisted players: {}", names.join(", "))
+            }
+        }
+        _ => unreachable!(),
+    };
+
+    replies.send(CommandReply::new(invocation.sender, text));
+
+    if !settings.whitelist {
+        replies.send(CommandReply::new(
+            invocation.sender,
+            "The whitelist is not enabled, set 'whitelist = true' in the settings to enable it",
+        ));
+    }
+}
+
+/// Moderators can't ban or kick players with a higher permission level than their own.
+fn can_moderate(
+    sender: Entity,
+    username: &str,
+    database: &Database,
+    level_query: &Query<&PermissionLevel>,
+) -> bool {
+    let sender_level = level_query.get(sender).copied().unwrap_or_default();
+    PermissionLevel::load(username, database) <= sender_level
+}
+
+fn ban_command(
+    In(invocation): In<CommandInvocation>,
+    net: Res<Server>,
+    database: Res<Database>,
+    player_query: Query<(Entity, &Player)>,
+    level_query: Query<&PermissionLevel>,
+    mut replies: EventWriter<CommandReply>,
+) {
+    // Shared by /ban and /tempban, /tempban has the duration as the second word.
+    let (username, duration, reason) = match invocation.arguments.as_slice() {
+        [Argument::Word(username)] => (username, None, ""),
+        [Argument::Word(username), Argument::Text(reason)] => (username, None, reason.as_str()),
+        [Argument::Word(username), Argument::Word(duration)] => (username, Some(duration), ""),
+        [Argument::Word(username), Argument::Word(duration), Argument::Text(reason)] => {
+            (username, Some(duration), reason.as_str())
+        }
+        _ => unreachable!(),
+    };
+
+    let expires = match duration {
+        Some(duration) => match parse_duration(duration) {
+            Some(seconds) => Some(unix_time() + seconds),
+            None => {
+                replies.send(CommandReply::error(
+                    invocation.sender,
+                    format!(
+                        "'{}' is not a valid duration, use e.g. 30m, 12h or 7d",
+                        duration
+                    ),
+                ));
+                return;
+            }
+        },
+        None => None,
+    };
+
+    if !can_moderate(invocation.sender, username, &database, &level_query) {
+        replies.send(CommandReply::error(
+            invocation.sender,
+            format!("{} has a higher permission level than you", username),
+        ));
+        return;
+    }
+
+    let ban = Ban {
+        reason: reason.to_owned(),
+        expires,
+    };
+    ban.save(username, &database);
+
+    if let Some((player_entity, _)) = player_query
+        .iter()
+        .find(|(_, player)| &player.username == username)
+    {
+        disconnect_with_reason(&net, player_entity, &ban.message());
+    }
+
+    let text = match duration {
+        Some(duration) => format!("{} was banned for {}", username, duration),
+        None => format!("{} was banned", username),
+    };
+    info!("{}", text);
+    replies.send(CommandReply::new(invocation.sender, text));
+}
+
+fn unban_command(
+    In(invocation): In<CommandInvocation>,
+    database: Res<Database>,
+    mut replies: EventWriter<CommandReply>,
+) {
+    let [Argument::Word(username)] = invocation.arguments.as_slice() else {
+        unreachable!()
+    };
+
+    let conn = database.get_connection();
+    let removed = conn
+        .execute("DELETE FROM bans WHERE name = ?", [username])
+        .unwrap();
+
+    if removed == 0 {
+        replies.send(CommandReply::error(
+            invocation.sender,
+            format!("{} is not banned", username),
+        ));
+        return;
+    }
+
+    info!("{} was unbanned", username);
+    replies.send(CommandReply::new(
+        invocation.sender,
+        format!("{} was unbanned", username),
+    ));
+}
+
+fn kick_command(
+    In(invocation): In<CommandInvocation>,
+    net: Res<Server>,
+    database: Res<Database>,
+    player_query: Query<&Player>,
+    level_query: Query<&PermissionLevel>,
+    mut replies: EventWriter<CommandReply>,
+) {
+    let (player_entity, reason) = match invocation.arguments.as_slice() {
+        [Argument::Player(player_entity)] => (*player_entity, None),
+        [Argument::Player(player_entity), Argument::Text(reason)] => (*player_entity, Some(reason)),
+        _ => unreachable!(),
+    };
+
+    let username = &player_query.get(player_entity).unwrap().username;
+
+    if !can_moderate(invocation.sender, username, &database, &level_query) {
+        replies.send(CommandReply::error(
+            invocation.sender,
+            format!("{} has a higher permission level than you", username),
+        ));
+        return;
+    }
+
+    let message = match reason {
+        Some(reason) => format!("You were kicked from the server\nReason: {}", reason),
+        None => "You were kicked from the server".to_owned(),
+    };
+    disconnect_with_reason(&net, player_entity, &message);
+
+    info!("{} was kicked", username);
+    replies.send(CommandReply::new(
+        invocation.sender,
+        format!("{} was kicked", username),
+    ));
+}
@@ -16,7 +16,7 @@ use fmc::{
 
 use crate::{
     items::{DroppedItem, ItemRegistry, ItemUseSystems, ItemUses},
-    players::Hotbar,
+    players::{Hotbar, Rejected},
 };
 
 pub struct HandPlugin;
@@ -51,11 +51,13 @@ impl HandInteractions {
 
 fn handle_left_clicks(
     mut clicks: EventReader<NetworkMessage<messages::LeftClick>>,
-    player_query: Query<(&Targets, &Camera, &GlobalTransform), With<Player>>,
+    player_query: Query<(&Targets, &Camera, &GlobalTransform), (With<Player>, Without<Rejected>)>,
     mut block_breaking_events: ResMut<MiningEvents>,
 ) {
     for click in clicks.read() {
-        let (targets, camera, transform) = player_query.get(click.player_entity).unwrap();
+        let Ok((targets, camera, transform)) = player_query.get(click.player_entity) else {
+            continue;
+        };
 
         let camera_position = transform.translation() + camera.translation;
 
@@ -107,7 +109,7 @@ fn break_blocks(
     net: Res<Server>,
     items: Res<Items>,
     chunk_subscriptions: Res<ChunkSubscriptions>,
-    hotbar_query: Query<&Hotbar, With<Player>>,
+    hotbar_query: Query<&Hotbar, (With<Player>, Without<Rejected>)>,
     mut model_query: Query<(&mut Model, &mut ModelVisibility), With<BreakingBlockMarker>>,
     mut block_update_writer: EventWriter<BlockUpdate>,
     mut mining_events: ResMut<MiningEvents>,
@@ -128,7 +130,9 @@ fn break_blocks(
             continue;
         };
 
-        let hotbar = hotbar_query.get(player_entity).unwrap();
+        let Ok(hotbar) = hotbar_query.get(player_entity) else {
+            continue;
+        };
 
         let tool_config = if let Some(item) = hotbar.held_item_stack().item() {
             Some(items.get_config(&item.id))
@@ -484,7 +488,7 @@ fn handle_right_clicks(
     model_map: Res<ModelMap>,
     chunk_subscriptions: Res<ChunkSubscriptions>,
     model_query: Query<(&Collider, &GlobalTransform), (With<Model>, Without<BlockPosition>)>,
-    mut player_query: Query<(&mut Hotbar, &Targets), With<Player>>,
+    mut player_query: Query<(&mut Hotbar, &Targets), (With<Player>, Without<Rejected>)>,
     mut item_use_query: Query<&mut ItemUses>,
     mut hand_interaction_query: Query<&mut HandInteractions>,
     mut block_update_writer: EventWriter<BlockUpdate>,
@@ -502,7 +506,9 @@ fn handle_right_clicks(
     }
 
     for right_click in clicks.read() {
-        let (mut hotbar, targets) = player_query.get_mut(right_click.player_entity).unwrap();
+        let Ok((mut hotbar, targets)) = player_query.get_mut(right_click.player_entity) else {
+            continue;
+        };
 
         let mut action = ActionOrder::Interact;
 
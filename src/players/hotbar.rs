@@ -1,16 +1,50 @@
 use fmc::{
+    bevy::math::DVec3,
+    items::{ItemStack, Items},
     networking::{NetworkMessage, Server},
-    players::Player,
+    physics::Physics,
+    players::{Camera, Player},
     prelude::*,
     protocol::messages,
 };
 
-use crate::players::Hotbar;
+use crate::{
+    commands::{
+        Argument, ChatCommand, CommandAppExt, CommandInvocation, CommandReply, Parameter,
+        PermissionLevel,
+    },
+    items::{DroppedItem, PickupCooldown},
+    players::{item_boxes::add_item_boxes, Hotbar, Inventory, Rejected},
+};
 
 pub struct HotbarPlugin;
 impl Plugin for HotbarPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
+        app.add_command(
+            ChatCommand::new("give")
+                .usage([
+                    Parameter::player("player"),
+                    Parameter::item("item"),
+                    Parameter::integer("count").optional(),
+                ])
+                .help("Give items to a player, what doesn't fit is dropped at their feet"),
+            give_command,
+        )
+        .add_command(
+            ChatCommand::new("clear")
+                .usage([Parameter::player("player").optional()])
+                .help("Remove all items from a player's hotbar and inventory"),
+            clear_command,
+        )
+        .add_command(
+            ChatCommand::new("drop")
+                .usage([Parameter::literal("all").optional()])
+                .permission(PermissionLevel::PLAYER)
+                .input()
+                .help("Throw one of the held item, or the whole stack with 'all'"),
+            drop_command,
+        )
+        .add_systems(
             Update,
             (initialize_interface, send_server_updates, equip_item),
         );
@@ -32,28 +66,15 @@ fn initialize_interface(net: Res<Server>, new_player_query: Query<Entity, Added<
 fn send_server_updates(net: Res<Server>, hotbar_query: Query<(Entity, &Hotbar), Changed<Hotbar>>) {
     for (player_entity, hotbar) in hotbar_query.iter() {
         let mut hotbar_node = messages::InterfaceItemBoxUpdate::default();
-        for (i, item_stack) in hotbar.iter().enumerate() {
-            if let Some(item) = item_stack.item() {
-                hotbar_node.add_itembox(
-                    "hotbar",
-                    i as u32,
-                    item.id,
-                    item_stack.size(),
-                    item.properties["durability"].as_u64().map(|v| v as u32),
-                    item.properties["description"].as_str(),
-                );
-            } else {
-                hotbar_node.add_empty_itembox("hotbar", i as u32);
-            }
-        }
-
+        add_item_boxes(&mut hotbar_node, "hotbar", hotbar);
         net.send_one(player_entity, hotbar_node);
     }
 }
+
 fn equip_item(
     net: Res<Server>,
     mut equip_events: EventReader<NetworkMessage<messages::InterfaceEquipItem>>,
-    mut hotbar: Query<&mut Hotbar>,
+    mut hotbar: Query<&mut Hotbar, Without<Rejected>>,
 ) {
     for equip_event in equip_events.read() {
         if equip_event.interface_path != "hotbar" {
@@ -65,7 +86,163 @@ fn equip_item(
             continue;
         }
 
-        let mut hotbar = hotbar.get_mut(equip_event.player_entity).unwrap();
+        let Ok(mut hotbar) = hotbar.get_mut(equip_event.player_entity) else {
+            continue;
+        };
         hotbar.equipped_item = equip_event.index as usize;
     }
 }
+
+/// How many stacks '/give' may drop on the ground when the player's inventory is full.
+const MAX_DROPPED_STACKS: u32 = 4;
+
+fn give_command(
+    In(invocation): In<CommandInvocation>,
+    mut commands: Commands,
+    items: Res<Items>,
+    mut player_query: Query<(&mut Hotbar, &mut Inventory, &Transform), With<Player>>,
+    mut replies: EventWriter<CommandReply>,
+) {
+    let (player_entity, item_id, count) = match invocation.arguments.as_slice() {
+        [Argument::Player(player_entity), Argument::Item(item_id)] => (player_entity, item_id, 1),
+        [Argument::Player(player_entity), Argument::Item(item_id), Argument::Integer(count)] => {
+            (player_entity, item_id, *count)
+        }
+        _ => unreachable!(),
+    };
+
+    let Some(count) = u32::try_from(count).ok().filter(|count| *count > 0) else {
+        replies.send(CommandReply::error(
+            invocation.sender,
+            "The count must be a positive number",
+        ));
+        return;
+    };
+
+    let Ok((mut hotbar, mut inventory, transform)) = player_query.get_mut(*player_entity) else {
+        replies.send(CommandReply::error(
+            invocation.sender,
+            "The player hasn't finished joining yet",
+        ));
+        return;
+    };
+
+    let item_config = items.get_config(item_id);
+
+    // What doesn't fit is dropped, one entity per stack, so only a few stacks may overflow.
+    let room: u32 = hotbar
+        .iter()
+        .chain(inventory.iter())
+        .map(|slot| {
+            if slot.is_empty() {
+                item_config.max_stack_size
+            } else if slot.item().is_some_and(|item| item.id == *item_id) {
+                slot.remaining_capacity()
+            } else {
+                0
+            }
+        })
+        .sum();
+    let max_count = room + MAX_DROPPED_STACKS * item_config.max_stack_size;
+    if count > max_count {
+        replies.send(CommandReply::error(
+            invocation.sender,
+            format!(
+                "Can give at most {} {}, what fits and {} stacks to drop",
+                max_count, item_config.name, MAX_DROPPED_STACKS
+            ),
+        ));
+        return;
+    }
+
+    let mut remaining = count;
+    while remaining > 0 {
+        let stack_size = remaining.min(item_config.max_stack_size);
+        remaining -= stack_size;
+
+        let mut item_stack = ItemStack::new(item_config, stack_size);
+        hotbar.insert_stack(&mut item_stack);
+        inventory.insert_stack(&mut item_stack);
+
+        if !item_stack.is_empty() {
+            commands.spawn((
+                DroppedItem::new(item_stack),
+                Transform::from_translation(transform.translation),
+            ));
+        }
+    }
+
+    replies.send(CommandReply::new(
+        invocation.sender,
+        format!("Gave {} {}", count, item_config.name),
+    ));
+}
+
+fn clear_command(
+    In(invocation): In<CommandInvocation>,
+    mut player_query: Query<(&Player, &mut Hotbar, &mut Inventory)>,
+    mut replies: EventWriter<CommandReply>,
+) {
+    let player_entity = match invocation.arguments.as_slice() {
+        [] => invocation.sender,
+        [Argument::Player(player_entity)] => *player_entity,
+        _ => unreachable!(),
+    };
+
+    let Ok((player, mut hotbar, mut inventory)) = player_query.get_mut(player_entity) else {
+        replies.send(CommandReply::error(
+            invocation.sender,
+            "Specify which player's items to clear",
+        ));
+        return;
+    };
+
+    for item_stack in hotbar.iter_mut().chain(inventory.iter_mut()) {
+        *item_stack = ItemStack::default();
+    }
+
+    replies.send(CommandReply::new(
+        invocation.sender,
+        format!("Cleared the items of {}", player.username),
+    ));
+}
+
+fn drop_command(
+    In(invocation): In<CommandInvocation>,
+    mut commands: Commands,
+    mut player_query: Query<(&mut Hotbar, &Transform, &Camera), With<Player>>,
+) {
+    let Ok((mut hotbar, transform, camera)) = player_query.get_mut(invocation.sender) else {
+        return;
+    };
+
+    let held_item_stack = hotbar.held_item_stack();
+    if held_item_stack.is_empty() {
+        return;
+    }
+
+    let amount = match invocation.arguments.as_slice() {
+        [] => 1,
+        [Argument::Word(_)] => held_item_stack.size(),
+        _ => unreachable!(),
+    };
+
+    let mut dropped = ItemStack::default();
+    hotbar
+        .held_item_stack_mut()
+        .transfer_to(&mut dropped, amount);
+
+    // Thrown from just in front of the face, slightly upwards so it arcs.
+    let forward = camera.forward();
+    let camera_position = transform.translation + camera.translation;
+    commands.spawn((
+        DroppedItem::new(dropped),
+        PickupCooldown::default(),
+        Transform::from_translation(camera_position + forward * 0.3 - DVec3::Y * 0.3),
+        Physics {
+            enabled: true,
+            velocity: forward * 6.0 + DVec3::Y * 2.0,
+            ..default()
+        },
+    ));
+}
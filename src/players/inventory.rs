@@ -0,0 +1,43 @@
+use fmc::{items::ItemStack, networking::Server, prelude::*, protocol::messages};
+
+use crate::players::{item_boxes::add_item_boxes, Hotbar, Inventory};
+
+pub(super) const INTERFACE_PATH: &str = "inventory";
+/// Item boxes of the inventory interface
+pub(super) const STORAGE_PATH: &str = "inventory/storage";
+/// The hotbar is shown in the inventory interface too, so items can be moved between them.
+pub(super) const HOTBAR_PATH: &str = "inventory/hotbar";
+
+pub struct InventoryPlugin;
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, send_server_updates);
+    }
+}
+
+/// The item stack a player has picked up from an item box in the inventory interface and not
+/// yet placed anywhere.
+#[derive(Component, Default, Deref, DerefMut, Clone)]
+pub struct HeldItemStack(ItemStack);
+
+fn send_server_updates(
+    net: Res<Server>,
+    inventory_query: Query<
+        (Entity, Ref<Inventory>, Ref<Hotbar>),
+        Or<(Changed<Inventory>, Changed<Hotbar>)>,
+    >,
+) {
+    for (player_entity, inventory, hotbar) in inventory_query.iter() {
+        let mut update = messages::InterfaceItemBoxUpdate::default();
+
+        if inventory.is_changed() {
+            add_item_boxes(&mut update, STORAGE_PATH, &inventory);
+        }
+
+        if hotbar.is_changed() {
+            add_item_boxes(&mut update, HOTBAR_PATH, &hotbar);
+        }
+
+        net.send_one(player_entity, update);
+    }
+}
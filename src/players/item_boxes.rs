@@ -0,0 +1,306 @@
+use fmc::{
+    items::ItemStack,
+    networking::{NetworkMessage, Server},
+    players::Player,
+    prelude::*,
+    protocol::messages,
+};
+
+use crate::{
+    items::DroppedItem,
+    players::{inventory, HeldItemStack, Hotbar, Inventory},
+};
+
+/// Moving items between item boxes. The client takes items from a box into the stack it holds
+/// on its cursor, and places them from there into another box. Between them these cover moving,
+/// splitting, merging and swapping stacks.
+///
+/// The client applies the moves on its own before the server has seen them. When a move turns
+/// out to be invalid, the item box is sent back to it the way the server has it, and what it holds
+/// on the cursor is reset.
+pub struct ItemBoxPlugin;
+impl Plugin for ItemBoxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (take_items, place_items).chain());
+    }
+}
+
+/// The interfaces players can move items in and out of.
+// TODO: Containers. They're not owned by the player, so will need to check that the player
+// has the container open.
+#[derive(Clone, Copy)]
+enum ItemBoxes {
+    Inventory,
+    Hotbar,
+}
+
+impl ItemBoxes {
+    fn from_path(interface_path: &str) -> Option<Self> {
+        match interface_path {
+            inventory::STORAGE_PATH => Some(Self::Inventory),
+            inventory::HOTBAR_PATH => Some(Self::Hotbar),
+            _ => None,
+        }
+    }
+
+    fn slots<'a>(self, hotbar: &'a Hotbar, inventory: &'a Inventory) -> &'a [ItemStack] {
+        match self {
+            Self::Inventory => inventory,
+            Self::Hotbar => hotbar,
+        }
+    }
+
+    // Only the component that is changed should trigger change detection, so this takes the
+    // 'Mut's instead of references to the components.
+    fn slots_mut<'a>(
+        self,
+        hotbar: &'a mut Mut<Hotbar>,
+        inventory: &'a mut Mut<Inventory>,
+    ) -> &'a mut [ItemStack] {
+        match self {
+            Self::Inventory => &mut inventory[..],
+            Self::Hotbar => &mut hotbar[..],
+        }
+    }
+}
+
+/// Add an item box update for a single slot.
+pub(super) fn add_item_box(
+    update: &mut messages::InterfaceItemBoxUpdate,
+    interface_path: &str,
+    index: u32,
+    item_stack: &ItemStack,
+) {
+    if let Some(item) = item_stack.item() {
+        update.add_itembox(
+            interface_path,
+            index,
+            item.id,
+            item_stack.size(),
+            item.properties["durability"].as_u64().map(|v| v as u32),
+            item.properties["description"].as_str(),
+        );
+    } else {
+        update.add_empty_itembox(interface_path, index);
+    }
+}
+
+/// Add an item box update for each of the slots.
+pub(super) fn add_item_boxes(
+    update: &mut messages::InterfaceItemBoxUpdate,
+    interface_path: &str,
+    slots: &[ItemStack],
+) {
+    for (i, item_stack) in slots.iter().enumerate() {
+        add_item_box(update, interface_path, i as u32, item_stack);
+    }
+}
+
+/// Resend a slot the client has the wrong idea about.
+fn send_correction(
+    net: &Server,
+    player_entity: Entity,
+    interface_path: &str,
+    index: u32,
+    item_stack: &ItemStack,
+) {
+    let mut update = messages::InterfaceItemBoxUpdate::default();
+    add_item_box(&mut update, interface_path, index, item_stack);
+    net.send_one(player_entity, update);
+}
+
+/// The cursor can't be corrected directly. Instead the held stack is put back into the player's
+/// slots and the inventory is closed, which makes the client let go of what it thinks it holds.
+/// Changing the slots sends them to the client again.
+fn reset_held_stack(
+    commands: &mut Commands,
+    net: &Server,
+    player_entity: Entity,
+    transform: &Transform,
+    hotbar: &mut Hotbar,
+    inventory: &mut Inventory,
+    held_item_stack: &mut ItemStack,
+) {
+    hotbar.insert_stack(held_item_stack);
+    inventory.insert_stack(held_item_stack);
+    if !held_item_stack.is_empty() {
+        commands.spawn((
+            DroppedItem::new(std::mem::take(held_item_stack)),
+            Transform::from_translation(transform.translation),
+        ));
+    }
+
+    net.send_one(
+        player_entity,
+        messages::InterfaceVisibilityUpdate {
+            interface_path: inventory::INTERFACE_PATH.to_owned(),
+            visible: false,
+        },
+    );
+}
+
+// The slots are looked up on the player that sent the message, so a player can only ever touch
+// their own items. A path or index the server hasn't sent can't be the client being out of sync,
+// so those are disconnected.
+fn take_items(
+    mut commands: Commands,
+    net: Res<Server>,
+    mut player_query: Query<
+        (&mut Hotbar, &mut Inventory, &mut HeldItemStack, &Transform),
+        With<Player>,
+    >,
+    mut take_events: EventReader<NetworkMessage<messages::InterfaceTakeItem>>,
+) {
+    for take_event in take_events.read() {
+        let Ok((mut hotbar, mut inventory, mut held_item_stack, transform)) =
+            player_query.get_mut(take_event.player_entity)
+        else {
+            continue;
+        };
+
+        let Some(item_boxes) = ItemBoxes::from_path(&take_event.interface_path) else {
+            net.disconnect(take_event.player_entity);
+            continue;
+        };
+
+        let index = take_event.index as usize;
+        let Some(slot) = item_boxes.slots(&hotbar, &inventory).get(index) else {
+            net.disconnect(take_event.player_entity);
+            continue;
+        };
+
+        // Can only take what is in the slot, and only pick it up if it can be held together
+        // with what is already held.
+        if take_event.quantity == 0
+            || take_event.quantity > slot.size()
+            || (!held_item_stack.is_empty() && held_item_stack.item() != slot.item())
+        {
+            send_correction(
+                &net,
+                take_event.player_entity,
+                &take_event.interface_path,
+                take_event.index,
+                slot,
+            );
+            reset_held_stack(
+                &mut commands,
+                &net,
+                take_event.player_entity,
+                transform,
+                &mut hotbar,
+                &mut inventory,
+                &mut held_item_stack,
+            );
+            continue;
+        }
+
+        let slot = &mut item_boxes.slots_mut(&mut hotbar, &mut inventory)[index];
+        let slot_before = slot.size();
+        slot.transfer_to(&mut held_item_stack, take_event.quantity);
+
+        // The held stack filled up, the client thinks it holds more than it does.
+        if slot_before - slot.size() < take_event.quantity {
+            send_correction(
+                &net,
+                take_event.player_entity,
+                &take_event.interface_path,
+                take_event.index,
+                slot,
+            );
+            reset_held_stack(
+                &mut commands,
+                &net,
+                take_event.player_entity,
+                transform,
+                &mut hotbar,
+                &mut inventory,
+                &mut held_item_stack,
+            );
+        }
+    }
+}
+
+fn place_items(
+    mut commands: Commands,
+    net: Res<Server>,
+    mut player_query: Query<
+        (&mut Hotbar, &mut Inventory, &mut HeldItemStack, &Transform),
+        With<Player>,
+    >,
+    mut place_events: EventReader<NetworkMessage<messages::InterfacePlaceItem>>,
+) {
+    for place_event in place_events.read() {
+        let Ok((mut hotbar, mut inventory, mut held_item_stack, transform)) =
+            player_query.get_mut(place_event.player_entity)
+        else {
+            continue;
+        };
+
+        let Some(item_boxes) = ItemBoxes::from_path(&place_event.interface_path) else {
+            net.disconnect(place_event.player_entity);
+            continue;
+        };
+
+        let index = place_event.index as usize;
+        let Some(slot) = item_boxes.slots(&hotbar, &inventory).get(index) else {
+            net.disconnect(place_event.player_entity);
+            continue;
+        };
+
+        // Placing a stack on top of a different item swaps them, which only works for the
+        // whole stack.
+        let swap = !slot.is_empty() && slot.item() != held_item_stack.item();
+
+        if place_event.quantity == 0
+            || place_event.quantity > held_item_stack.size()
+            || (swap && place_event.quantity != held_item_stack.size())
+        {
+            send_correction(
+                &net,
+                place_event.player_entity,
+                &place_event.interface_path,
+                place_event.index,
+                slot,
+            );
+            reset_held_stack(
+                &mut commands,
+                &net,
+                place_event.player_entity,
+                transform,
+                &mut hotbar,
+                &mut inventory,
+                &mut held_item_stack,
+            );
+            continue;
+        }
+
+        let slot = &mut item_boxes.slots_mut(&mut hotbar, &mut inventory)[index];
+        if swap {
+            std::mem::swap(slot, &mut **held_item_stack);
+            continue;
+        }
+
+        let held_before = held_item_stack.size();
+        held_item_stack.transfer_to(slot, place_event.quantity);
+
+        // The client thinks all of it fit.
+        if held_before - held_item_stack.size() < place_event.quantity {
+            send_correction(
+                &net,
+                place_event.player_entity,
+                &place_event.interface_path,
+                place_event.index,
+                slot,
+            );
+            reset_held_stack(
+                &mut commands,
+                &net,
+                place_event.player_entity,
+                transform,
+                &mut hotbar,
+                &mut inventory,
+                &mut held_item_stack,
+            );
+        }
+    }
+}
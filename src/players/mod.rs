@@ -18,24 +18,47 @@ use fmc::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::world::WorldProperties;
+use crate::{
+    commands::{Argument, ChatCommand, CommandAppExt, CommandInvocation, CommandReply, Parameter},
+    items::DroppedItem,
+    world::WorldProperties,
+};
 
+mod admission;
 mod hand;
 mod hotbar;
+mod inventory;
+mod item_boxes;
+mod teleport;
 
+pub use admission::{disconnect_with_reason, AdmitPlayers, Rejected};
 pub use hand::HandInteractions;
+pub use inventory::HeldItemStack;
 
 pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<RespawnEvent>()
+            .add_plugins(admission::AdmissionPlugin)
             .add_plugins(hand::HandPlugin)
             .add_plugins(hotbar::HotbarPlugin)
+            .add_plugins(inventory::InventoryPlugin)
+            .add_plugins(item_boxes::ItemBoxPlugin)
+            .add_plugins(teleport::TeleportPlugin)
+            .add_command(
+                ChatCommand::new("gamemode")
+                    .usage([
+                        Parameter::choice("mode", &["0", "1", "survival", "creative"]),
+                        Parameter::player("player").optional(),
+                    ])
+                    .help("Change your own or another player's game mode"),
+                gamemode_command,
+            )
             .add_systems(
                 Update,
                 (
                     on_gamemode_update,
-                    (add_players, apply_deferred).chain(),
+                    (add_players, apply_deferred).chain().after(AdmitPlayers),
                     respawn_players,
                     rotate_player_model,
                 ),
@@ -77,6 +100,75 @@ impl Hotbar {
         let index = self.equipped_item;
         &mut self[index]
     }
+
+    /// Move as much of the item stack into the hotbar as there is room for. Stacks of the same
+    /// item are filled first, then empty slots.
+    pub fn insert_stack(&mut self, item_stack: &mut ItemStack) {
+        insert_stack(&mut self.items, item_stack);
+    }
+
+    /// If any of the item stack can be inserted.
+    pub fn has_room_for(&self, item_stack: &ItemStack) -> bool {
+        has_room_for(&self.items, item_stack)
+    }
+}
+
+/// The player's storage beside the hotbar.
+#[derive(Component, Serialize, Deserialize, Deref, DerefMut, Clone)]
+pub struct Inventory {
+    #[deref]
+    items: Vec<ItemStack>,
+}
+
+impl Inventory {
+    pub const SIZE: usize = 27;
+
+    /// Move as much of the item stack into the inventory as there is room for. Stacks of the
+    /// same item are filled first, then empty slots.
+    pub fn insert_stack(&mut self, item_stack: &mut ItemStack) {
+        insert_stack(&mut self.items, item_stack);
+    }
+
+    /// If any of the item stack can be inserted.
+    pub fn has_room_for(&self, item_stack: &ItemStack) -> bool {
+        has_room_for(&self.items, item_stack)
+    }
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self {
+            items: vec![ItemStack::default(); Self::SIZE],
+        }
+    }
+}
+
+fn insert_stack(slots: &mut [ItemStack], item_stack: &mut ItemStack) {
+    for slot in slots.iter_mut() {
+        if item_stack.is_empty() {
+            return;
+        }
+
+        if slot.item() == item_stack.item() {
+            item_stack.transfer_to(slot, u32::MAX);
+        }
+    }
+
+    for slot in slots.iter_mut() {
+        if item_stack.is_empty() {
+            return;
+        }
+
+        if slot.is_empty() {
+            item_stack.transfer_to(slot, u32::MAX);
+        }
+    }
+}
+
+fn has_room_for(slots: &[ItemStack], item_stack: &ItemStack) -> bool {
+    slots.iter().any(|slot| {
+        slot.is_empty() || (slot.item() == item_stack.item() && slot.remaining_capacity() != 0)
+    })
 }
 
 /// Default bundle used for new players.
@@ -86,6 +178,8 @@ pub struct PlayerBundle {
     camera: Camera,
     aabb: Collider,
     hotbar: Hotbar,
+    inventory: Inventory,
+    held_item_stack: HeldItemStack,
     gamemode: GameMode,
 }
 
@@ -96,6 +190,8 @@ impl Default for PlayerBundle {
             camera: Camera::default(),
             aabb: Collider::from_min_max(DVec3::new(-0.3, 0.0, -0.3), DVec3::new(0.3, 1.8, 0.3)),
             hotbar: Hotbar::default(),
+            inventory: Inventory::default(),
+            held_item_stack: HeldItemStack::default(),
             gamemode: GameMode::Survival,
         }
     }
@@ -111,6 +207,7 @@ impl From<PlayerSave> for PlayerBundle {
                 ..default()
             }),
             hotbar: save.hotbar,
+            inventory: save.inventory,
             gamemode: save.game_mode,
             ..default()
         }
@@ -126,6 +223,9 @@ pub struct PlayerSave {
     camera_position: DVec3,
     camera_rotation: DQuat,
     hotbar: Hotbar,
+    // Saves from before the inventory existed don't have it
+    #[serde(default)]
+    inventory: Inventory,
     game_mode: GameMode,
 }
 
@@ -170,7 +270,7 @@ fn add_players(
     database: Res<Database>,
     models: Res<Models>,
     mut respawn_events: EventWriter<RespawnEvent>,
-    added_players: Query<(Entity, &Player), Added<Player>>,
+    added_players: Query<(Entity, &Player), (Added<Player>, Without<Rejected>)>,
 ) {
     for (player_entity, player) in added_players.iter() {
         let bundle = if let Some(save) = PlayerSave::load(&player.username, &database) {
@@ -218,24 +318,50 @@ fn add_players(
 }
 
 fn save_player_data(
+    mut commands: Commands,
     database: Res<Database>,
     mut network_events: EventReader<NetworkEvent>,
-    mut players: Query<(&Player, &Transform, &Camera, &Hotbar, &GameMode)>,
+    mut players: Query<(
+        &Player,
+        &Transform,
+        &Camera,
+        &Hotbar,
+        &Inventory,
+        &HeldItemStack,
+        &GameMode,
+    )>,
 ) {
     for network_event in network_events.read() {
         let NetworkEvent::Disconnected { entity } = network_event else {
             continue;
         };
 
-        let Ok((player, transform, camera, hotbar, game_mode)) = players.get_mut(*entity) else {
+        let Ok((player, transform, camera, hotbar, inventory, held_item_stack, game_mode)) =
+            players.get_mut(*entity)
+        else {
             continue;
         };
 
+        let mut hotbar = hotbar.clone();
+        let mut inventory = inventory.clone();
+
+        // The player might have been moving an item in the inventory interface.
+        let mut held_item_stack = ItemStack::clone(held_item_stack);
+        hotbar.insert_stack(&mut held_item_stack);
+        inventory.insert_stack(&mut held_item_stack);
+        if !held_item_stack.is_empty() {
+            commands.spawn((
+                DroppedItem::new(held_item_stack),
+                Transform::from_translation(transform.translation),
+            ));
+        }
+
         PlayerSave {
             position: transform.translation,
             camera_position: camera.translation,
             camera_rotation: camera.rotation,
-            hotbar: hotbar.clone(),
+            hotbar,
+            inventory,
             game_mode: *game_mode,
         }
         .save(&player.username, &database);
@@ -320,11 +446,13 @@ fn respawn_players(
 // This needs to be fixed on its own, but it will also become necessary to handle the player's
 // models directly, as there will be a small collection of them.
 fn rotate_player_model(
-    mut player_query: Query<&mut Transform, With<Player>>,
+    mut player_query: Query<&mut Transform, (With<Player>, Without<Rejected>)>,
     mut camera_rotation_events: EventReader<NetworkMessage<messages::PlayerCameraRotation>>,
 ) {
     for rotation_update in camera_rotation_events.read() {
-        let mut transform = player_query.get_mut(rotation_update.player_entity).unwrap();
+        let Ok(mut transform) = player_query.get_mut(rotation_update.player_entity) else {
+            continue;
+        };
 
         let rotation = rotation_update.rotation.as_dquat();
 
@@ -362,3 +490,29 @@ fn on_gamemode_update(
         }
     }
 }
+
+fn gamemode_command(
+    In(invocation): In<CommandInvocation>,
+    mut game_mode_query: Query<&mut GameMode>,
+    mut replies: EventWriter<CommandReply>,
+) {
+    let (mode, player_entity) = match invocation.arguments.as_slice() {
+        [Argument::Word(mode)] => (mode, invocation.sender),
+        [Argument::Word(mode), Argument::Player(player_entity)] => (mode, *player_entity),
+        _ => unreachable!(),
+    };
+
+    let Ok(mut game_mode) = game_mode_query.get_mut(player_entity) else {
+        replies.send(CommandReply::error(
+            invocation.sender,
+            "Specify which player's game mode to change",
+        ));
+        return;
+    };
+
+    *game_mode = match mode.as_str() {
+        "0" | "survival" => GameMode::Survival,
+        "1" | "creative" => GameMode::Creative,
+        _ => unreachable!(),
+    };
+}
@@ -0,0 +1,251 @@
+use fmc::{
+    bevy::{
+        math::DVec3,
+        tasks::{AsyncComputeTaskPool, Task},
+    },
+    blocks::{BlockPosition, Blocks},
+    database::Database,
+    networking::Server,
+    players::Player,
+    prelude::*,
+    protocol::messages,
+    world::{
+        chunk::{Chunk, ChunkPosition},
+        WorldMap,
+    },
+};
+
+use crate::{
+    commands::{
+        Argument, ChatCommand, CommandAppExt, CommandInvocation, CommandReply, Parameter,
+        PermissionLevel,
+    },
+    players::RespawnEvent,
+};
+
+pub struct TeleportPlugin;
+impl Plugin for TeleportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_command(
+            ChatCommand::new("tp")
+                .usage([Parameter::position("position")])
+                .usage([Parameter::player("player")])
+                .usage([Parameter::player("player"), Parameter::player("target")])
+                .help("Teleport to a position or a player, or teleport a player to another"),
+            tp_command,
+        )
+        .add_command(
+            ChatCommand::new("spawn")
+                .usage([Parameter::player("player").optional()])
+                .permission(PermissionLevel::PLAYER)
+                .help("Teleport to the spawn point, operators can send other players there"),
+            spawn_command,
+        )
+        .add_systems(Update, finish_teleports);
+    }
+}
+
+/// A teleport to a position that waits for an open spot to be found, so the player doesn't
+/// end up inside the terrain.
+#[derive(Component)]
+struct PendingTeleport(Task<DVec3>);
+
+fn tp_command(
+    In(invocation): In<CommandInvocation>,
+    mut commands: Commands,
+    net: Res<Server>,
+    world_map: Res<WorldMap>,
+    database: Res<Database>,
+    mut player_query: Query<&mut Transform, With<Player>>,
+    mut replies: EventWriter<CommandReply>,
+) {
+    let (player_entity, target) = match invocation.arguments.as_slice() {
+        [Argument::Position(position)] => {
+            if !player_query.contains(invocation.sender) {
+                replies.send(CommandReply::error(
+                    invocation.sender,
+                    "Only players can be teleported",
+                ));
+                return;
+            }
+
+            // Searching for an open spot has to load chunks, which would stall the server if
+            // done here.
+            let position = *position;
+            let terrain_generator = world_map.terrain_generator.clone();
+            let database = database.clone();
+            let task = AsyncComputeTaskPool::get().spawn(async move {
+                find_open_position(position, |chunk_position| {
+                    futures_lite::future::block_on(Chunk::load(
+                        chunk_position,
+                        terrain_generator.clone(),
+                        database.clone(),
+                    ))
+                    .1
+                })
+            });
+
+            commands
+                .entity(invocation.sender)
+                .try_insert(PendingTeleport(task));
+            return;
+        }
+        [Argument::Player(target)] => (invocation.sender, *target),
+        [Argument::Player(player_entity), Argument::Player(target)] => (*player_entity, *target),
+        _ => unreachable!(),
+    };
+
+    let Ok(position) = player_query
+        .get(target)
+        .map(|transform| transform.translation)
+    else {
+        replies.send(CommandReply::error(
+            invocation.sender,
+            "The player hasn't finished joining yet",
+        ));
+        return;
+    };
+
+    let Ok(mut transform) = player_query.get_mut(player_entity) else {
+        replies.send(CommandReply::error(
+            invocation.sender,
+            "Only players can be teleported",
+        ));
+        return;
+    };
+
+    transform.translation = position;
+
+    net.send_one(player_entity, messages::PlayerPosition { position });
+}
+
+fn finish_teleports(
+    mut commands: Commands,
+    net: Res<Server>,
+    mut player_query: Query<(Entity, &mut Transform, &mut PendingTeleport)>,
+) {
+    for (player_entity, mut transform, mut pending) in player_query.iter_mut() {
+        let Some(position) =
+            futures_lite::future::block_on(futures_lite::future::poll_once(&mut pending.0))
+        else {
+            continue;
+        };
+
+        commands.entity(player_entity).remove::<PendingTeleport>();
+
+        transform.translation = position;
+        net.send_one(player_entity, messages::PlayerPosition { position });
+    }
+}
+
+fn spawn_command(
+    In(invocation): In<CommandInvocation>,
+    player_query: Query<(), With<Player>>,
+    permission_query: Query<&PermissionLevel>,
+    mut respawn_events: EventWriter<RespawnEvent>,
+    mut replies: EventWriter<CommandReply>,
+) {
+    let player_entity = match invocation.arguments.as_slice() {
+        [] => invocation.sender,
+        [Argument::Player(player_entity)] => *player_entity,
+        _ => unreachable!(),
+    };
+
+    let permission_level = permission_query
+        .get(invocation.sender)
+        .copied()
+        .unwrap_or_default();
+    if player_entity != invocation.sender && permission_level < PermissionLevel::OPERATOR {
+        replies.send(CommandReply::error(
+            invocation.sender,
+            "Only operators can send other players to spawn",
+        ));
+        return;
+    }
+
+    if !player_query.contains(player_entity) {
+        replies.send(CommandReply::error(
+            invocation.sender,
+            "Specify which player to send to spawn",
+        ));
+        return;
+    }
+
+    respawn_events.send(RespawnEvent { player_entity });
+}
+
+/// Find the first position at or above the given position where a player fits. The chunks are
+/// only read, the player's chunks are loaded the usual way once they have been moved.
+fn find_open_position(
+    position: DVec3,
+    mut load_chunk: impl FnMut(ChunkPosition) -> Chunk,
+) -> DVec3 {
+    // Stop looking after this many chunks, the player is moved to the original position.
+    const MAX_CHUNKS: usize = 8;
+
+    let air = Blocks::get().get_id("air");
+
+    let target = BlockPosition::from(position);
+    let mut chunk_position = ChunkPosition::from(target);
+    // Block the search starts from in the current chunk
+    let mut start = target;
+    // Block at the bottom of the current run of air blocks
+    let mut candidate = target;
+    let mut air_count = 0;
+    let mut found = None;
+
+    for _ in 0..MAX_CHUNKS {
+        let chunk = load_chunk(chunk_position);
+
+        let chunk_origin = BlockPosition::from(chunk_position);
+
+        if chunk.is_uniform() {
+            if chunk[0] == air {
+                found = Some(if air_count == 0 { start } else { candidate });
+                break;
+            }
+            air_count = 0;
+        } else {
+            let column_index = (0..Chunk::SIZE * Chunk::SIZE)
+                .find(|index| {
+                    let column = chunk_origin + BlockPosition::from(index * Chunk::SIZE);
+                    column.x == target.x && column.z == target.z
+                })
+                .unwrap();
+            let column = &chunk.blocks[column_index * Chunk::SIZE..][..Chunk::SIZE];
+            let first = (start.y - chunk_origin.y) as usize;
+
+            for (y, block) in column.iter().enumerate().skip(first) {
+                if *block != air {
+                    air_count = 0;
+                    continue;
+                }
+
+                if air_count == 0 {
+                    candidate = chunk_origin + BlockPosition::from(column_index * Chunk::SIZE + y);
+                }
+
+                air_count += 1;
+                if air_count == 2 {
+                    found = Some(candidate);
+                    break;
+                }
+            }
+
+            if found.is_some() {
+                break;
+            }
+        }
+
+        chunk_position.y += Chunk::SIZE as i32;
+        start = BlockPosition::from(chunk_position);
+    }
+
+    match found {
+        Some(open_position) if open_position.y != target.y => {
+            DVec3::new(position.x, open_position.y as f64, position.z)
+        }
+        // Either the target position was already open, or no open position was found.
+        _ => position,
+    }
+}
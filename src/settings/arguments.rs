@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+
+const USAGE: &str = "\
+Usage: fmc_vanilla [OPTIONS]
+
+Options:
+    --config <path>     Settings file to use, defaults to 'server_settings.toml'
+    --data-dir <path>   Directory the world, assets and settings are stored in
+    --world <name>      Name of the world to load
+    --seed <seed>       Seed used for terrain generation, a number or text
+    --port <port>       Not supported yet, the server always listens on fmc's default port
+    --print-config      Print the settings after all overrides are applied, then exit
+    --list-assets       Print the bundled assets and which package each is from, then exit
+    --help              Print this message
+
+Settings can also be set through environment variables named FMC_ followed by the name of the
+setting in uppercase with '-' replaced by '_', e.g. FMC_WORLD_NAME or FMC_SEED. The
+config file and data directory can be set with FMC_CONFIG and FMC_DATA_DIR. Command line options
+take precedence over environment variables, which take precedence over the settings file.";
+
+/// Options given on the command line. The config file and data directory fall back to their
+/// environment variables, the environment overrides for settings are applied when they are loaded.
+#[derive(Default, Debug)]
+pub struct Arguments {
+    pub config: Option<PathBuf>,
+    pub data_dir: Option<PathBuf>,
+    pub world: Option<String>,
+    pub seed: Option<String>,
+    // TODO: The listener is set up by fmc's networking plugin, which can't be given a port yet.
+    // Until it can, this is only accepted so it can be warned about.
+    pub port: Option<String>,
+    pub print_config: bool,
+    pub list_assets: bool,
+}
+
+impl Arguments {
+    /// Parse the command line. Prints the usage and exits if the arguments are invalid.
+    pub fn parse() -> Self {
+        let mut arguments = Self {
+            config: std::env::var_os("FMC_CONFIG").map(PathBuf::from),
+            data_dir: std::env::var_os("FMC_DATA_DIR").map(PathBuf::from),
+            ..Self::default()
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            // Both '--flag value' and '--flag=value' are accepted
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_owned(), Some(value.to_owned())),
+                None => (arg, None),
+            };
+
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .unwrap_or_else(|| exit_with_usage(&format!("Missing value for '{}'", flag)))
+            };
+
+            match flag.as_str() {
+                "--config" => arguments.config = Some(PathBuf::from(value())),
+                "--data-dir" => arguments.data_dir = Some(PathBuf::from(value())),
+                "--world" => arguments.world = Some(value()),
+                "--seed" => arguments.seed = Some(value()),
+                "--port" => arguments.port = Some(value()),
+                "--print-config" => arguments.print_config = true,
+                "--list-assets" => arguments.list_assets = true,
+                "--help" | "-h" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                _ => exit_with_usage(&format!("Unknown argument '{}'", flag)),
+            }
+        }
+
+        arguments
+    }
+}
+
+fn exit_with_usage(error: &str) -> ! {
+    eprintln!("{}\n\n{}", error, USAGE);
+    std::process::exit(1);
+}
@@ -0,0 +1,627 @@
+use fmc::prelude::*;
+use serde::{Deserialize, Deserializer};
+
+use std::{
+    hash::{DefaultHasher, Hasher},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::commands::{
+    ChatCommand, CommandAppExt, CommandInvocation, CommandReply, PermissionLevel,
+};
+
+mod arguments;
+
+pub use arguments::Arguments;
+
+const SETTINGS_PATH: &str = "./server_settings.toml";
+/// The settings file used before it was changed to toml.
+const OLD_SETTINGS_PATH: &str = "./server_settings.txt";
+
+pub struct SettingsPlugin;
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let arguments = Arguments::parse();
+
+        if arguments.list_assets {
+            print!("{}", crate::assets::ASSET_ORIGINS);
+            std::process::exit(0);
+        }
+
+        // The config path is given relative to where the server was started from.
+        let config_path = match &arguments.config {
+            Some(path) => std::path::absolute(path).unwrap_or_else(|error| {
+                panic!("Invalid config path '{}': {}", path.display(), error)
+            }),
+            None => PathBuf::from(SETTINGS_PATH),
+        };
+
+        // While developing we want all artifacts to go to a separate directory.
+        let data_dir = arguments.data_dir.clone().or_else(|| {
+            std::env::var_os("CARGO")
+                .is_some()
+                .then(|| PathBuf::from("server"))
+        });
+        if let Some(data_dir) = data_dir {
+            std::fs::create_dir_all(&data_dir)
+                .and_then(|_| std::env::set_current_dir(&data_dir))
+                .unwrap_or_else(|error| {
+                    panic!(
+                        "Could not use '{}' as the data directory: {}",
+                        data_dir.display(),
+                        error
+                    )
+                });
+        }
+
+        let (settings, warnings) =
+            Settings::load(&config_path, &arguments).unwrap_or_else(|error| panic!("{}", error));
+
+        if arguments.print_config {
+            for warning in warnings.iter() {
+                eprintln!("{}", warning);
+            }
+            print!("{}", settings.to_toml());
+            std::process::exit(0);
+        }
+
+        let source = SettingsSource {
+            modified: last_modified(&config_path),
+            path: config_path,
+            arguments,
+            watch_timer: Timer::new(std::time::Duration::from_secs(1), TimerMode::Repeating),
+        };
+
+        // Logging isn't set up until after this plugin is built, so the warnings have to wait.
+        app.insert_resource(settings)
+            .insert_resource(source)
+            .add_command(
+                ChatCommand::new("reload")
+                    .permission(PermissionLevel::ADMIN)
+                    .help("Reload the server settings"),
+                reload_command,
+            )
+            .add_systems(Startup, move || {
+                for warning in warnings.iter() {
+                    warn!("{}", warning);
+                }
+            })
+            .add_systems(Update, watch_settings_file);
+    }
+}
+
+/// Where the settings were loaded from, so they can be loaded again when the file changes.
+#[derive(Resource)]
+struct SettingsSource {
+    path: PathBuf,
+    arguments: Arguments,
+    /// When the file was last changed, if it exists.
+    modified: Option<SystemTime>,
+    watch_timer: Timer,
+}
+
+fn last_modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Settings are reloaded at runtime when the file changes. Systems that depend on a setting
+/// can react to it changing through change detection, the resource is only marked as changed
+/// when one of the values actually changes.
+#[derive(Resource, Deserialize, PartialEq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Settings {
+    /// Name of the world that should be loaded
+    #[serde(rename = "world-name", deserialize_with = "deserialize_database_path")]
+    pub database_path: String,
+    /// Seed used for terrain generation. The world stores the seed it was created with, so this
+    /// only applies to new worlds.
+    #[serde(deserialize_with = "deserialize_seed")]
+    pub seed: u64,
+    /// The seed the way it was hashed before worlds stored their seed. Worlds from back then
+    /// were generated from this, and must keep using it.
+    #[serde(skip)]
+    pub legacy_seed: u64,
+    /// Should pvp be enabled
+    pub pvp: bool,
+    /// The max render distance the server will provide for.
+    pub render_distance: u32,
+    /// How many chat lines are kept to show players when they join.
+    pub chat_history_size: usize,
+    /// Only let players on the whitelist join
+    pub whitelist: bool,
+    /// How many players can be online at once
+    pub max_players: usize,
+    /// Message of the day, shown to players when they join and in the server status.
+    pub motd: String,
+    /// Port the server status can be queried from, 0 to disable it.
+    pub status_port: u16,
+    /// Seconds before items dropped on the ground disappear, 0 to keep them forever.
+    pub item_despawn_time: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            database_path: "./world.sqlite".to_owned(),
+            seed: 1,
+            legacy_seed: 1,
+            pvp: false,
+            render_distance: 16,
+            chat_history_size: 100,
+            whitelist: false,
+            max_players: 20,
+            motd: String::new(),
+            status_port: 42070,
+            item_despawn_time: 300,
+        }
+    }
+}
+
+/// Names of all the settings, anything else in the file is warned about.
+const SETTING_NAMES: [&str; 10] = [
+    "world-name",
+    "seed",
+    "pvp",
+    "render-distance",
+    "chat-history-size",
+    "whitelist",
+    "max-players",
+    "motd",
+    "status-port",
+    "item-despawn-time",
+];
+
+fn deserialize_database_path<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<String, D::Error> {
+    let world_name = String::deserialize(deserializer)?;
+    Ok("./".to_owned() + &world_name + ".sqlite")
+}
+
+fn deserialize_seed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged, expecting = "a number or a text string")]
+    enum Seed {
+        Number(i64),
+        Text(String),
+    }
+
+    let seed = match Seed::deserialize(deserializer)? {
+        // Toml can't represent numbers above i64::MAX, so those wrap around to be negative.
+        Seed::Number(number) => number as u64,
+        Seed::Text(text) => hash_seed(&text),
+    };
+
+    Ok(seed)
+}
+
+/// Text seeds are hashed with 64-bit FNV-1a. It is written out here instead of using the
+/// standard library's hasher, as that one is not guaranteed to give the same output between
+/// Rust versions, and the same seed must always give the same world.
+pub fn hash_seed(seed: &str) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    let mut hash = OFFSET_BASIS;
+    for byte in seed.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(PRIME);
+    }
+    hash
+}
+
+/// Worlds used to hash the seed text with the standard library's hasher, the default seed was
+/// used as is. The text must be exactly what was written, '007' and '7' are different seeds.
+fn legacy_hash_seed(seed_text: Option<&str>) -> u64 {
+    let Some(text) = seed_text else {
+        return Settings::default().legacy_seed;
+    };
+
+    let mut hasher = DefaultHasher::new();
+    hasher.write(text.as_bytes());
+    hasher.finish()
+}
+
+/// Seeds that look like numbers are used as numbers, anything else as text.
+fn parse_seed(value: &str) -> toml::Value {
+    match value.trim().parse::<i64>() {
+        Ok(number) => toml::Value::Integer(number),
+        Err(_) => toml::Value::String(value.to_owned()),
+    }
+}
+
+/// Interpret a value from the command line or environment the way it would be read from the
+/// settings file, anything that isn't a valid toml value is taken as text.
+fn parse_override(value: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {}", value))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(value.to_owned()))
+}
+
+impl Settings {
+    /// Load the settings file and apply overrides from the environment and command line, returns
+    /// the settings along with warnings about the file's contents.
+    pub fn load(path: &Path, arguments: &Arguments) -> Result<(Self, Vec<String>), String> {
+        let mut warnings = Vec::new();
+
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            // Servers that were set up before the settings file was toml.
+            Err(_) if Path::new(OLD_SETTINGS_PATH).exists() => {
+                let contents = Self::migrate_old_settings(path)?;
+                warnings.push(format!(
+                    "The settings in '{}' were moved to '{}', the old file was renamed to '{}.old'",
+                    OLD_SETTINGS_PATH,
+                    path.display(),
+                    OLD_SETTINGS_PATH
+                ));
+                contents
+            }
+            Err(_) => {
+                std::fs::write(path, Self::default_file()).unwrap();
+                String::new()
+            }
+        };
+
+        // Both files exist, the old one was left behind or put back.
+        if Path::new(OLD_SETTINGS_PATH).exists() {
+            warnings.push(format!(
+                "'{}' is no longer used, move your settings to '{}'",
+                OLD_SETTINGS_PATH, SETTINGS_PATH
+            ));
+        }
+
+        // Parsed on its own first so errors in the file are reported with their line.
+        let mut table: toml::Table = toml::from_str::<Settings>(&contents)
+            .and_then(|_| toml::from_str(&contents))
+            .map_err(|error| format!("Error reading {}:\n{}", path.display(), error))?;
+
+        for name in table.keys() {
+            if SETTING_NAMES.contains(&name.as_str()) {
+                continue;
+            }
+
+            let line = contents
+                .lines()
+                .position(|line| line.split('=').next().map(str::trim) == Some(name.as_str()))
+                .map(|line_num| format!(" at line {}", line_num + 1))
+                .unwrap_or_default();
+
+            warnings.push(format!(
+                "Unknown setting '{}' in {}{}, it will be ignored. Valid settings are: {}",
+                name,
+                path.display(),
+                line,
+                SETTING_NAMES.join(", ")
+            ));
+        }
+
+        // The seed as it was written, numbers lose their formatting when parsed.
+        let mut seed_text = table.get("seed").map(|seed| match seed {
+            toml::Value::String(text) => text.clone(),
+            value => value.to_string(),
+        });
+
+        for name in SETTING_NAMES {
+            let variable = "FMC_".to_owned() + &name.to_uppercase().replace('-', "_");
+            if let Ok(value) = std::env::var(&variable) {
+                let value = match name {
+                    "seed" => {
+                        seed_text = Some(value.clone());
+                        parse_seed(&value)
+                    }
+                    // Text settings are taken as written, 'FMC_MOTD=true' shouldn't be a bool.
+                    "world-name" | "motd" => toml::Value::String(value),
+                    _ => parse_override(&value),
+                };
+                table.insert(name.to_owned(), value);
+            }
+        }
+
+        if let Some(world_name) = &arguments.world {
+            table.insert(
+                "world-name".to_owned(),
+                toml::Value::String(world_name.clone()),
+            );
+        }
+        if arguments.port.is_some() {
+            warnings.push(
+                "'--port' is not supported yet and is ignored, the server listens on fmc's \
+                default port"
+                    .to_owned(),
+            );
+        }
+        if let Some(seed) = &arguments.seed {
+            seed_text = Some(seed.clone());
+            table.insert("seed".to_owned(), parse_seed(seed));
+        }
+
+        let legacy_seed = legacy_hash_seed(seed_text.as_deref());
+
+        let mut settings = Settings::deserialize(toml::Value::Table(table)).map_err(|error| {
+            format!(
+                "Invalid setting given through the command line or environment:\n{}",
+                error
+            )
+        })?;
+        settings.legacy_seed = legacy_seed;
+
+        return Ok((settings, warnings));
+    }
+
+    /// Name of the world, the database path without the extension.
+    pub fn world_name(&self) -> &str {
+        self.database_path
+            .trim_start_matches("./")
+            .trim_end_matches(".sqlite")
+    }
+
+    /// The settings as they would be written in the settings file.
+    pub fn to_toml(&self) -> String {
+        format!(
+            "\
+world-name = {}
+seed = {}
+pvp = {}
+render-distance = {}
+chat-history-size = {}
+whitelist = {}
+max-players = {}
+motd = {}
+status-port = {}
+item-despawn-time = {}
+",
+            toml::Value::String(self.world_name().to_owned()),
+            self.seed as i64,
+            self.pvp,
+            self.render_distance,
+            self.chat_history_size,
+            self.whitelist,
+            self.max_players,
+            toml::Value::String(self.motd.clone()),
+            self.status_port,
+            self.item_despawn_time
+        )
+    }
+
+    /// Move the settings from the old file into a new toml file at the path, returns the contents
+    /// of the new file. The old file had one 'name = value' per line, where the values were
+    /// never quoted.
+    fn migrate_old_settings(path: &Path) -> Result<String, String> {
+        let old_contents = std::fs::read_to_string(OLD_SETTINGS_PATH)
+            .map_err(|error| format!("Could not read '{}': {}", OLD_SETTINGS_PATH, error))?;
+
+        let mut contents = Self::default_file();
+        contents += &format!("\n# Moved from '{}'\n", OLD_SETTINGS_PATH);
+
+        for line in old_contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((name, value)) = line.split_once('=') else {
+                return Err(format!(
+                    "Could not move the settings from '{}', '{}' is not of the format \
+                    'name = value'",
+                    OLD_SETTINGS_PATH, line
+                ));
+            };
+            let (name, value) = (name.trim(), value.trim());
+
+            let value = match name {
+                // The old file hashed the seed as text, even numbers. Keeping it as text lets
+                // the world keep generating the same way.
+                "seed" | "world-name" | "motd" => toml::Value::String(value.to_owned()),
+                _ => parse_override(value),
+            };
+            contents += &format!("{} = {}\n", name, value);
+        }
+
+        std::fs::write(path, &contents)
+            .map_err(|error| format!("Could not write '{}': {}", path.display(), error))?;
+        std::fs::rename(OLD_SETTINGS_PATH, OLD_SETTINGS_PATH.to_owned() + ".old")
+            .map_err(|error| format!("Could not rename '{}': {}", OLD_SETTINGS_PATH, error))?;
+
+        Ok(contents)
+    }
+
+    /// The contents of the settings file written when there isn't one.
+    fn default_file() -> String {
+        let settings = Self::default();
+
+        format!(
+            "\
+# Remove the '#' in front of a setting to change it.
+
+# Name of the world, it is saved to '<world-name>.sqlite'
+#world-name = \"{}\"
+
+# Seed used for terrain generation, can be a number or text. It is only used when a new world
+# is created, existing worlds keep the seed they were created with.
+#seed = \"my world\"
+
+# If players can damage each other, true or false
+#pvp = {}
+
+# The max render distance the server will provide for, in chunks
+#render-distance = {}
+
+# How many lines of chat are kept to show players when they join
+#chat-history-size = {}
+
+# Only let players that have been added with '/whitelist add' join, true or false
+#whitelist = {}
+
+# How many players can be online at once
+#max-players = {}
+
+# Message of the day, shown to players when they join
+#motd = \"Welcome!\"
+
+# Port the server status (name, motd, player count and version) can be queried from, 0 disables it
+#status-port = {}
+
+# Seconds before items dropped on the ground disappear, 0 keeps them forever
+#item-despawn-time = {}
+",
+            settings.world_name(),
+            settings.pvp,
+            settings.render_distance,
+            settings.chat_history_size,
+            settings.whitelist,
+            settings.max_players,
+            settings.status_port,
+            settings.item_despawn_time
+        )
+    }
+}
+
+/// Result of reloading the settings
+struct Reload {
+    /// Names of the settings that changed
+    changed: Vec<&'static str>,
+    warnings: Vec<String>,
+}
+
+fn reload_settings(
+    source: &SettingsSource,
+    settings: &mut ResMut<Settings>,
+) -> Result<Reload, String> {
+    let (mut new_settings, mut warnings) = Settings::load(&source.path, &source.arguments)?;
+
+    // These are only read when the server starts, changing them would have no effect or leave
+    // the world in an inconsistent state.
+    if new_settings.database_path != settings.database_path {
+        warnings.push(format!(
+            "'world-name' can't be changed while the server is running, it will stay '{}' until \
+            the server is restarted",
+            settings.world_name()
+        ));
+        new_settings.database_path = settings.database_path.clone();
+    }
+    if new_settings.seed != settings.seed {
+        warnings.push(
+            "'seed' can't be changed while the server is running, and is only used when a new \
+            world is created"
+                .to_owned(),
+        );
+        new_settings.seed = settings.seed;
+        new_settings.legacy_seed = settings.legacy_seed;
+    }
+    if new_settings.status_port != settings.status_port {
+        warnings.push(
+            "'status-port' can't be changed while the server is running, restart the server to \
+            apply it"
+                .to_owned(),
+        );
+        new_settings.status_port = settings.status_port;
+    }
+
+    let mut changed = Vec::new();
+    if new_settings.pvp != settings.pvp {
+        changed.push("pvp");
+    }
+    if new_settings.render_distance != settings.render_distance {
+        changed.push("render-distance");
+    }
+    if new_settings.chat_history_size != settings.chat_history_size {
+        changed.push("chat-history-size");
+    }
+    if new_settings.whitelist != settings.whitelist {
+        changed.push("whitelist");
+    }
+    if new_settings.max_players != settings.max_players {
+        changed.push("max-players");
+    }
+    if new_settings.motd != settings.motd {
+        changed.push("motd");
+    }
+    if new_settings.item_despawn_time != settings.item_despawn_time {
+        changed.push("item-despawn-time");
+    }
+
+    settings.set_if_neq(new_settings);
+
+    Ok(Reload { changed, warnings })
+}
+
+fn watch_settings_file(
+    time: Res<Time>,
+    mut source: ResMut<SettingsSource>,
+    mut settings: ResMut<Settings>,
+) {
+    source.watch_timer.tick(time.delta());
+    if !source.watch_timer.just_finished() {
+        return;
+    }
+
+    let modified = last_modified(&source.path);
+    if modified == source.modified {
+        return;
+    }
+    source.modified = modified;
+
+    match reload_settings(&source, &mut settings) {
+        Ok(reload) => {
+            for warning in reload.warnings.iter() {
+                warn!("{}", warning);
+            }
+            if !reload.changed.is_empty() {
+                info!("Settings reloaded, changed: {}", reload.changed.join(", "));
+            }
+        }
+        // Most likely caught while the file is being edited, the old settings are kept.
+        Err(error) => error!("Failed to reload settings, keeping the old ones: {}", error),
+    }
+}
+
+fn reload_command(
+    In(invocation): In<CommandInvocation>,
+    mut source: ResMut<SettingsSource>,
+    mut settings: ResMut<Settings>,
+    mut replies: EventWriter<CommandReply>,
+) {
+    // Stop the file watcher from reloading a second time.
+    let modified = last_modified(&source.path);
+    source.modified = modified;
+
+    let reload = match reload_settings(&source, &mut settings) {
+        Ok(reload) => reload,
+        Err(error) => {
+            replies.send(CommandReply::error(invocation.sender, error));
+            return;
+        }
+    };
+
+    for warning in reload.warnings {
+        warn!("{}", warning);
+        replies.send(CommandReply::error(invocation.sender, warning));
+    }
+
+    let text = if reload.changed.is_empty() {
+        "Settings reloaded, nothing changed".to_owned()
+    } else {
+        info!("Settings reloaded, changed: {}", reload.changed.join(", "));
+        format!("Settings reloaded, changed: {}", reload.changed.join(", "))
+    };
+    replies.send(CommandReply::new(invocation.sender, text));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::hash_seed;
+
+    // Changing the output would change the terrain of every world with a text seed.
+    #[test]
+    fn hash_seed_is_stable() {
+        assert_eq!(hash_seed(""), 0xcbf29ce484222325);
+        assert_eq!(hash_seed("a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(hash_seed("foobar"), 0x85944171f73967e8);
+        assert_eq!(hash_seed("my world"), 0xcc222cc04703fecf);
+    }
+}
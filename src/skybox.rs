@@ -2,10 +2,21 @@ use std::time::Duration;
 
 use fmc::{networking::Server, prelude::*, protocol::messages};
 
+use crate::commands::{Argument, ChatCommand, CommandAppExt, CommandInvocation, Parameter};
+
 pub struct SkyPlugin;
 impl Plugin for SkyPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(Clock::default())
+            .add_command(
+                ChatCommand::new("time")
+                    .usage([Parameter::choice(
+                        "time",
+                        &["noon", "midnight", "sunrise", "sunset"],
+                    )])
+                    .help("Set the time of day"),
+                time_command,
+            )
             .add_systems(Update, day_night_cycle);
     }
 }
@@ -50,3 +61,17 @@ fn day_night_cycle(time: Res<Time>, net: Res<Server>, mut clock: ResMut<Clock>)
 
     net.broadcast(message);
 }
+
+fn time_command(In(invocation): In<CommandInvocation>, mut clock: ResMut<Clock>) {
+    let [Argument::Word(time)] = invocation.arguments.as_slice() else {
+        unreachable!()
+    };
+
+    match time.as_str() {
+        "noon" => clock.set_noon(),
+        "midnight" => clock.set_midnight(),
+        "sunrise" => clock.set_sunrise(),
+        "sunset" => clock.set_sunset(),
+        _ => unreachable!(),
+    }
+}
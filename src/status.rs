@@ -0,0 +1,93 @@
+use std::{
+    io::Write,
+    net::TcpListener,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use fmc::{players::Player, prelude::*};
+use serde::Serialize;
+
+use crate::{players::Rejected, settings::Settings};
+
+/// Answers status queries from server lists and monitoring. Anyone that connects to the status
+/// port is sent the server status as a single line of json, and the connection is closed.
+pub struct StatusPlugin;
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup)
+            .add_systems(Update, update_status);
+    }
+}
+
+#[derive(Serialize)]
+struct ServerStatus<'a> {
+    name: &'a str,
+    motd: &'a str,
+    online_players: usize,
+    max_players: usize,
+    version: &'static str,
+    /// Hash of the bundled assets
+    assets: &'static str,
+}
+
+/// The json that is sent in response to a query, shared with the listener thread.
+#[derive(Resource)]
+struct StatusResponse(Arc<RwLock<String>>);
+
+fn setup(mut commands: Commands, settings: Res<Settings>) {
+    let response = Arc::new(RwLock::new(String::new()));
+    commands.insert_resource(StatusResponse(response.clone()));
+
+    if settings.status_port == 0 {
+        return;
+    }
+
+    let listener = match TcpListener::bind(("0.0.0.0", settings.status_port)) {
+        Ok(listener) => listener,
+        Err(error) => {
+            error!(
+                "Could not listen for status queries on port {}: {}",
+                settings.status_port, error
+            );
+            return;
+        }
+    };
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+
+            // Whatever the client sends is ignored, everyone gets the same answer.
+            let response = response.read().unwrap().clone();
+            stream.set_write_timeout(Some(Duration::from_secs(1))).ok();
+            stream.write_all(response.as_bytes()).ok();
+        }
+    });
+}
+
+fn update_status(
+    settings: Res<Settings>,
+    response: Res<StatusResponse>,
+    player_query: Query<(), (With<Player>, Without<Rejected>)>,
+    mut last_online: Local<Option<usize>>,
+) {
+    let online_players = player_query.iter().count();
+    if !settings.is_changed() && *last_online == Some(online_players) {
+        return;
+    }
+    *last_online = Some(online_players);
+
+    let status = ServerStatus {
+        name: settings.world_name(),
+        motd: &settings.motd,
+        online_players,
+        max_players: settings.max_players,
+        version: env!("CARGO_PKG_VERSION"),
+        assets: crate::assets::ASSETS_HASH,
+    };
+
+    *response.0.write().unwrap() = serde_json::to_string(&status).unwrap() + "\n";
+}
@@ -0,0 +1,19 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds since the unix epoch.
+pub(crate) fn unix_time() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Format a number of seconds in the largest unit that fits, e.g. '12 hours'.
+pub(crate) fn format_duration(seconds: i64) -> String {
+    match seconds {
+        ..60 => format!("{} seconds", seconds.max(1)),
+        60..3600 => format!("{} minutes", seconds / 60),
+        3600..86400 => format!("{} hours", seconds / 3600),
+        _ => format!("{} days", seconds / 86400),
+    }
+}
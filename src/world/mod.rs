@@ -6,7 +6,10 @@ use fmc::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::settings::Settings;
+use crate::{
+    commands::{ChatCommand, CommandAppExt, CommandInvocation, CommandReply},
+    settings::Settings,
+};
 
 mod biomes;
 pub mod blocks;
@@ -16,6 +19,10 @@ pub struct WorldPlugin;
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(blocks::BlocksPlugin)
+            .add_command(
+                ChatCommand::new("seed").help("Show the seed of the world"),
+                seed_command,
+            )
             .add_systems(Startup, setup)
             .add_systems(
                 Update,
@@ -30,13 +37,49 @@ fn setup(
     blocks: Res<Blocks>,
     settings: Res<Settings>,
 ) {
-    let properties = WorldProperties::load(database).unwrap_or(WorldProperties::default());
+    let (mut properties, new_world) = match WorldProperties::load(database) {
+        Some(properties) => (properties, false),
+        None => (WorldProperties::default(), true),
+    };
+
+    let seed = match properties.seed {
+        Some(seed) => {
+            if seed != settings.seed && seed != settings.legacy_seed {
+                warn!(
+                    "The world was created with the seed {}, the seed in the settings is ignored",
+                    seed as i64
+                );
+            }
+            seed
+        }
+        None if new_world => {
+            properties.seed = Some(settings.seed);
+            settings.seed
+        }
+        // The world was created before the seed was stored, back when it was hashed every time
+        // the server started.
+        None => {
+            properties.seed = Some(settings.legacy_seed);
+            settings.legacy_seed
+        }
+    };
+
     commands.insert_resource(properties);
 
-    commands.insert_resource(WorldMap::new(terrain_generation::Earth::new(
-        settings.seed,
-        &blocks,
-    )));
+    commands.insert_resource(WorldMap::new(terrain_generation::Earth::new(seed, &blocks)));
+}
+
+fn seed_command(
+    In(invocation): In<CommandInvocation>,
+    properties: Res<WorldProperties>,
+    mut replies: EventWriter<CommandReply>,
+) {
+    // Shown the way it would be written in the settings file to get the same world.
+    let seed = properties.seed.unwrap() as i64;
+    replies.send(CommandReply::new(
+        invocation.sender,
+        format!("Seed: {}", seed),
+    ));
 }
 
 fn save_world_properties(database: Res<Database>, properties: Res<WorldProperties>) {
@@ -48,6 +91,9 @@ pub struct WorldProperties {
     // TODO: This must be set to a valid spawn point when first inserted, currently it is just
     // ignored.
     pub spawn_point: SpawnPoint,
+    /// Seed the world was created with, terrain is always generated from this one.
+    #[serde(default)]
+    pub seed: Option<u64>,
 }
 
 impl WorldProperties {